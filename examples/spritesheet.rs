@@ -0,0 +1,38 @@
+//! Save all sprites as an image: `cargo run --example spritesheet -- [seed] [path]`
+use anyhow::Result;
+use specs_blit::PixelBuffer;
+use std::{fs::File, io::BufWriter, path::Path};
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let seed = args.next().and_then(|seed| seed.parse().ok()).unwrap_or(0);
+    let path = args.next().unwrap_or_else(|| "sprites.png".to_string());
+
+    write_png(&ld46::spritesheet::render(seed)?, &path)?;
+    println!("Saved the sprites of seed {} to {}", seed, path);
+
+    Ok(())
+}
+
+fn write_png<P: AsRef<Path>>(buffer: &PixelBuffer, path: P) -> Result<()> {
+    // The pixels are stored as ABGR, convert them to RGB
+    let data = buffer
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let [r, g, b, _] = pixel.to_le_bytes();
+            vec![r, g, b]
+        })
+        .collect::<Vec<_>>();
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        buffer.width() as u32,
+        buffer.height() as u32,
+    );
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+
+    Ok(())
+}
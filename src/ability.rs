@@ -0,0 +1,202 @@
+use crate::{
+    color::Palette,
+    entity::Lifetime,
+    gui,
+    input::{Input, RemoteInput},
+    particle::ParticleEmitter,
+    physics::*,
+    player::{Controller, Player},
+};
+use miniquad::KeyCode;
+use specs_blit::{specs::*, PixelBuffer};
+
+/// Frames before the dash can be used again.
+const DASH_COOLDOWN: f64 = 180.0;
+/// Frames lasers can't hurt the paddle after dashing.
+const DASH_INVULNERABLE: f64 = 30.0;
+/// How far a keyboard controlled paddle dashes.
+const DASH_DISTANCE: f64 = 60.0;
+/// Frames before the barrier can be used again.
+const BARRIER_COOLDOWN: f64 = 600.0;
+/// Frames the barrier protects the planet.
+const BARRIER_TIME: f64 = 120.0;
+/// Width of the barrier drawn along the planet edge.
+const BARRIER_WIDTH: usize = 2;
+/// Width of the cooldown meters on the HUD.
+const METER_WIDTH: usize = 40;
+
+impl Controller {
+    /// Whether the dash button is pressed.
+    fn dashing(self, input: &Input, remote: &RemoteInput) -> bool {
+        match self {
+            Controller::Mouse => input.key_down(KeyCode::Q),
+            Controller::Keyboard => {
+                input.key_down(KeyCode::LeftShift) || input.key_down(KeyCode::RightShift)
+            }
+            Controller::Remote => remote.key_down(KeyCode::Q),
+            Controller::Bot => false,
+        }
+    }
+
+    /// Whether the barrier button is pressed.
+    fn raising_barrier(self, input: &Input, remote: &RemoteInput) -> bool {
+        match self {
+            Controller::Mouse => input.key_down(KeyCode::E),
+            Controller::Keyboard => {
+                input.key_down(KeyCode::LeftControl) || input.key_down(KeyCode::RightControl)
+            }
+            Controller::Remote => remote.key_down(KeyCode::E),
+            Controller::Bot => false,
+        }
+    }
+}
+
+/// Move the paddle instantly and be safe from lasers for a short while.
+#[derive(Component, Debug, Default)]
+pub struct Dash {
+    cooldown: f64,
+    invulnerable: f64,
+}
+
+impl Dash {
+    /// Whether lasers can't hurt the paddle.
+    pub fn invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+
+    /// Draw the cooldown meter.
+    pub fn render(&self, buffer: &mut PixelBuffer, palette: &Palette, x: usize, y: usize) {
+        render_cooldown(buffer, palette, x, y, self.cooldown / DASH_COOLDOWN);
+    }
+}
+
+/// Protect the planet from projectiles for a short while.
+#[derive(Component, Debug, Default)]
+pub struct Barrier {
+    cooldown: f64,
+    time_left: f64,
+}
+
+impl Barrier {
+    pub fn active(&self) -> bool {
+        self.time_left > 0.0
+    }
+
+    /// Draw the cooldown meter and the barrier itself when it's active.
+    pub fn render(&self, buffer: &mut PixelBuffer, palette: &Palette, x: usize, y: usize) {
+        render_cooldown(buffer, palette, x, y, self.cooldown / BARRIER_COOLDOWN);
+
+        if self.active() {
+            gui::draw_meter(
+                buffer,
+                palette,
+                (0, 0),
+                (BARRIER_WIDTH, crate::HEIGHT),
+                1.0,
+                palette.heal,
+            );
+        }
+    }
+}
+
+/// Draw a meter that fills up while the ability is cooling down.
+fn render_cooldown(buffer: &mut PixelBuffer, palette: &Palette, x: usize, y: usize, cooldown: f64) {
+    let color = if cooldown <= 0.0 {
+        palette.ui
+    } else {
+        palette.ui_inactive
+    };
+
+    gui::draw_meter(
+        buffer,
+        palette,
+        (x, y),
+        (METER_WIDTH, 2),
+        1.0 - cooldown,
+        color,
+    );
+}
+
+/// System that activates the abilities of the paddles.
+pub struct AbilitySystem;
+impl<'a> System<'a> for AbilitySystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Input>,
+        Read<'a, RemoteInput>,
+        Read<'a, Palette>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Dash>,
+        WriteStorage<'a, Barrier>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, BoundingBox>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            input,
+            remote,
+            palette,
+            player,
+            mut dash,
+            mut barrier,
+            mut pos,
+            mut vel,
+            bb,
+            updater,
+        ): Self::SystemData,
+    ) {
+        for (player, dash, pos, vel, bb) in (&player, &mut dash, &mut pos, &mut vel, &bb).join() {
+            dash.cooldown = (dash.cooldown - 1.0).max(0.0);
+            dash.invulnerable = (dash.invulnerable - 1.0).max(0.0);
+
+            let controller = player.controller();
+            if dash.cooldown > 0.0 || !controller.dashing(&input, &remote) {
+                continue;
+            }
+
+            let center = pos.y + bb.y / 2.0;
+            let target = match controller {
+                Controller::Mouse => input.mouse_y() as f64,
+                Controller::Remote => remote.mouse_y() as f64,
+                _ => center + Controller::keyboard_direction(&input) * DASH_DISTANCE,
+            };
+
+            // Leave a cloud behind at the old position
+            let emitter = entities.create();
+            updater.insert(
+                emitter,
+                ParticleEmitter::new(15.0, palette.ui)
+                    .with_burst(16)
+                    .with_gradient(palette.ui, palette.ui_background)
+                    .with_fade()
+                    .with_size(2.0, 1.0)
+                    .with_dispersion(1.5)
+                    .with_drag(0.9)
+                    .with_offset(bb.center_offset()),
+            );
+            updater.insert(emitter, pos.clone());
+            updater.insert(emitter, Lifetime::new(2.0));
+
+            pos.y = (target - bb.y / 2.0).clamp(0.0, crate::HEIGHT as f64 - bb.y);
+            vel.y = 0.0;
+
+            dash.cooldown = DASH_COOLDOWN;
+            dash.invulnerable = DASH_INVULNERABLE;
+        }
+
+        for (player, barrier) in (&player, &mut barrier).join() {
+            barrier.cooldown = (barrier.cooldown - 1.0).max(0.0);
+            barrier.time_left = (barrier.time_left - 1.0).max(0.0);
+
+            if barrier.cooldown <= 0.0 && player.controller().raising_barrier(&input, &remote) {
+                barrier.cooldown = BARRIER_COOLDOWN;
+                barrier.time_left = BARRIER_TIME;
+            }
+        }
+    }
+}
@@ -1,35 +1,88 @@
-use crate::{color, random};
-use std::ptr;
+use crate::color;
+
+type Vec2 = vek::Vec2<f64>;
 
-const STARS_BRIGHT: usize = 200;
-const STARS_DIM: usize = 200;
 const RED_COLORS: usize = 500;
 const GREEN_COLORS: usize = 100;
 const BLUE_COLORS: usize = 200;
 
+/// Pixels the nebula clouds move every frame.
+const NEBULA_SPEED: f64 = 0.05;
+/// The amount of stars, their color and the pixels they move every frame, from far to close.
+const STAR_LAYERS: [(usize, u32, f64); 3] = [
+    (200, color::STAR_DIM, 0.1),
+    (150, color::STAR_BRIGHT, 0.25),
+    (50, color::FOREGROUND, 0.6),
+];
+/// Fraction of the stars that twinkle.
+const TWINKLE_CHANCE: f64 = 0.2;
+/// How fast the stars twinkle.
+const TWINKLE_SPEED: f64 = 0.05;
+/// Chance every frame that a comet appears when there's none.
+const COMET_CHANCE: f64 = 1.0 / 900.0;
+const COMET_SPEED: f64 = 3.0;
+/// Length of the tail of a comet in pixels.
+const COMET_TAIL: usize = 16;
+
 const SIZE: usize = crate::WIDTH * crate::HEIGHT;
 
+/// A star that moves with the layer it's in.
+struct Star {
+    pos: Vec2,
+    /// Offset of the twinkling wave, stars without one don't twinkle.
+    twinkle: Option<f64>,
+}
+
+/// Stars at the same distance, the closer the faster they move.
+struct Layer {
+    stars: Vec<Star>,
+    color: u32,
+    speed: f64,
+}
+
+/// A bright dot with a tail that crosses the screen once in a while.
+struct Comet {
+    pos: Vec2,
+    vel: Vec2,
+}
+
+/// The scrolling space behind everything: drifting nebula clouds with layers of stars in front.
 pub struct Background {
-    buffer: Vec<u32>,
+    /// The pre-rendered nebula clouds, copied wrapped around every frame.
+    nebula: Vec<u32>,
+    layers: Vec<Layer>,
+    comet: Option<Comet>,
+    /// Frames since the start.
+    time: f64,
+    /// The seed it was generated from.
+    origin: u64,
+    /// State of the random generator for the animations, separate from the game so drawing
+    /// doesn't change the simulation.
+    seed: u64,
 }
 
 impl Background {
-    pub fn new() -> Self {
+    /// Generate the clouds and stars, the same seed always gives the same background.
+    pub fn new(seed: u64) -> Self {
+        // Zero would make the generator only return zeros
+        let mut state = seed | 1;
+        let mut index = || (xorshift(&mut state) * SIZE as f64) as usize;
+
         let mut buffer: Vec<[u8; 3]> = vec![[0x05; 3]; SIZE];
-        (0..RED_COLORS).for_each(|_| buffer[random::usize(SIZE)][0] = 0xFF);
-        (0..GREEN_COLORS).for_each(|_| buffer[random::usize(SIZE)][1] = 0xFF);
-        (0..BLUE_COLORS).for_each(|_| buffer[random::usize(SIZE)][2] = 0xFF);
+        (0..RED_COLORS).for_each(|_| buffer[index()][0] = 0xFF);
+        (0..GREEN_COLORS).for_each(|_| buffer[index()][1] = 0xFF);
+        (0..BLUE_COLORS).for_each(|_| buffer[index()][2] = 0xFF);
 
         fastblur::gaussian_blur(&mut buffer, crate::WIDTH, crate::HEIGHT, 10.0);
 
-        (0..RED_COLORS).for_each(|_| buffer[random::usize(SIZE)][0] = 0xFF);
-        (0..GREEN_COLORS).for_each(|_| buffer[random::usize(SIZE)][1] = 0xFF);
-        (0..BLUE_COLORS).for_each(|_| buffer[random::usize(SIZE)][2] = 0xFF);
+        (0..RED_COLORS).for_each(|_| buffer[index()][0] = 0xFF);
+        (0..GREEN_COLORS).for_each(|_| buffer[index()][1] = 0xFF);
+        (0..BLUE_COLORS).for_each(|_| buffer[index()][2] = 0xFF);
 
         fastblur::gaussian_blur(&mut buffer, crate::WIDTH, crate::HEIGHT, 7.0);
 
         // Convert [R, G, B] to [u32]
-        let mut buffer = buffer
+        let nebula = buffer
             .into_iter()
             .map(|rgb| {
                 u32::from(rgb[0])
@@ -40,15 +93,142 @@ impl Background {
             .collect::<Vec<_>>();
 
         // Add some nice colors pretty stars
-        (0..STARS_BRIGHT).for_each(|_| buffer[random::usize(SIZE)] = color::STAR_BRIGHT);
-        (0..STARS_DIM).for_each(|_| buffer[random::usize(SIZE)] = color::STAR_DIM);
+        let layers = STAR_LAYERS
+            .iter()
+            .map(|(amount, color, speed)| Layer {
+                stars: (0..*amount)
+                    .map(|_| Star {
+                        pos: Vec2::new(
+                            xorshift(&mut state) * crate::WIDTH as f64,
+                            xorshift(&mut state) * crate::HEIGHT as f64,
+                        ),
+                        twinkle: if xorshift(&mut state) < TWINKLE_CHANCE {
+                            Some(xorshift(&mut state) * std::f64::consts::TAU)
+                        } else {
+                            None
+                        },
+                    })
+                    .collect(),
+                color: *color,
+                speed: *speed,
+            })
+            .collect();
+
+        Self {
+            nebula,
+            layers,
+            comet: None,
+            time: 0.0,
+            origin: seed,
+            seed: state,
+        }
+    }
+
+    /// The seed it was generated from.
+    pub fn origin(&self) -> u64 {
+        self.origin
+    }
+
+    /// Frames since the start.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
 
-        Self { buffer }
+    /// Jump to a moment in time, used to follow the background of another game.
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
     }
 
-    pub fn copy(&self, buffer: &mut Vec<u32>) {
-        unsafe {
-            ptr::copy(self.buffer.as_ptr(), buffer.as_mut_ptr(), SIZE);
+    /// Move everything a frame further.
+    pub fn update(&mut self) {
+        self.time += 1.0;
+
+        self.comet = match self.comet.take() {
+            Some(comet) => {
+                let pos = comet.pos + comet.vel;
+                // Wait until the tail also left the screen
+                let tail = COMET_TAIL as f64;
+                if pos.x < -tail || pos.y > crate::HEIGHT as f64 + tail {
+                    None
+                } else {
+                    Some(Comet { pos, ..comet })
+                }
+            }
+            None if self.random() < COMET_CHANCE => {
+                // Fly in from the top towards the bottom left
+                let angle = 0.3 + self.random() * 0.6;
+                Some(Comet {
+                    pos: Vec2::new(self.random() * crate::WIDTH as f64 * 1.5, 0.0),
+                    vel: Vec2::new(-angle.cos(), angle.sin()) * COMET_SPEED,
+                })
+            }
+            None => None,
+        };
+    }
+
+    /// Draw the background into the buffer, moved by the camera offset and wrapped around the
+    /// edges.
+    pub fn copy(&self, buffer: &mut [u32], (offset_x, offset_y): (i32, i32)) {
+        let (width, height) = (crate::WIDTH, crate::HEIGHT);
+
+        // The nebula is drawn on every pixel so it doesn't have to be cleared
+        let x = (offset_x - (self.time * NEBULA_SPEED) as i32).rem_euclid(width as i32) as usize;
+        let y = offset_y.rem_euclid(height as i32) as usize;
+        for (row, target) in buffer.chunks_exact_mut(width).take(height).enumerate() {
+            let source = &self.nebula[(row + height - y) % height * width..][..width];
+            target[x..].copy_from_slice(&source[..width - x]);
+            target[..x].copy_from_slice(&source[width - x..]);
+        }
+
+        let wrap = |pos: Vec2| {
+            (
+                (pos.x as i32 + offset_x).rem_euclid(width as i32) as usize,
+                (pos.y as i32 + offset_y).rem_euclid(height as i32) as usize,
+            )
+        };
+
+        for layer in self.layers.iter() {
+            let scroll = Vec2::new(self.time * layer.speed, 0.0);
+            for star in layer.stars.iter() {
+                let color = match star.twinkle {
+                    Some(phase) => {
+                        let brightness = ((self.time * TWINKLE_SPEED + phase).sin() + 1.0) / 2.0;
+                        color::dim(layer.color, 0.3 + brightness * 0.7)
+                    }
+                    None => layer.color,
+                };
+
+                let (x, y) = wrap(star.pos - scroll);
+                buffer[y * width + x] = color;
+            }
         }
+
+        if let Some(comet) = self.comet.as_ref() {
+            let direction = comet.vel.normalized();
+            for i in 0..COMET_TAIL {
+                let pos = comet.pos - direction * i as f64;
+                if pos.x < 0.0 || pos.y < 0.0 || pos.x >= width as f64 || pos.y >= height as f64 {
+                    continue;
+                }
+
+                let (x, y) = wrap(pos);
+                buffer[y * width + x] =
+                    color::dim(color::FOREGROUND, 1.0 - i as f64 / COMET_TAIL as f64);
+            }
+        }
+    }
+
+    /// A random number between 0 and 1 for the animations.
+    fn random(&mut self) -> f64 {
+        xorshift(&mut self.seed)
     }
 }
+
+/// A random number between 0 and 1 from a xorshift generator.
+fn xorshift(state: &mut u64) -> f64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+
+    (*state >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,186 @@
+use crate::{
+    enemy::Enemy,
+    physics::*,
+    player::{Controller, Player},
+    projectile::{Laser, Projectile},
+    upgrade::HoldProjectile,
+};
+use specs_blit::specs::*;
+
+type Vec2 = vek::Vec2<f64>;
+
+/// Frames the paddle keeps moving after releasing the controls, used to brake in time.
+const BRAKE_FRAMES: f64 = 5.7;
+/// Distance from the target at which the paddle stops moving at full speed.
+const SLOWDOWN_DISTANCE: f64 = 4.0;
+/// The furthest from its center the paddle can reflect something without missing it.
+const MAX_AIM_OFFSET: f64 = 16.0;
+/// Horizontal distance between the projectile and the point it's reflected around.
+const REFLECT_DISTANCE: f64 = 25.0;
+/// The maximum vertical distance a paddle covers per frame.
+const MAX_PADDLE_SPEED: f64 = 5.5;
+/// Distance kept from the lanes of the lasers.
+const LASER_MARGIN: f64 = 8.0;
+
+/// Something that's going to reach the paddle.
+#[derive(Debug)]
+struct Threat {
+    /// Frames until it reaches the paddle.
+    time: f64,
+    /// The vertical position of its center when it reaches the paddle.
+    y: f64,
+    /// The speed of projectiles, enemies can't be aimed because they are destroyed when touched.
+    projectile_speed: Option<f64>,
+}
+
+/// Predict where something moving towards the paddle will arrive, bouncing from the top and the
+/// bottom of the screen like the `BoundingBoxSystem` does.
+fn predict(pos: Vec2, vel: Vec2, size: Vec2, paddle_x: f64) -> Option<(f64, f64)> {
+    if vel.x >= 0.0 || pos.x < paddle_x {
+        return None;
+    }
+
+    let time = (pos.x - paddle_x) / -vel.x;
+
+    // Fold the unbounded position back into the screen
+    let range = crate::HEIGHT as f64 - size.y;
+    let y = (pos.y + vel.y * time).rem_euclid(range * 2.0);
+    let y = if y > range { range * 2.0 - y } else { y };
+
+    Some((time, y + size.y / 2.0))
+}
+
+/// Where the paddle center should be to send a projectile arriving at `y` towards the best enemy.
+fn aim(y: f64, paddle_x: f64, speed: f64, enemies: &[(Vec2, Vec2)]) -> f64 {
+    enemies
+        .iter()
+        .filter(|(pos, _)| pos.x > paddle_x + REFLECT_DISTANCE)
+        .map(|(pos, vel)| {
+            // Lead the target by the time the projectile needs to reach it
+            let distance = pos.x - paddle_x;
+            let target_y = pos.y + vel.y * distance / speed.max(0.1);
+
+            let offset = (y - target_y) / distance * REFLECT_DISTANCE;
+
+            // Prefer enemies that can be hit and are close by
+            let score = offset.abs().max(MAX_AIM_OFFSET) + distance * 0.01;
+
+            (score, offset.clamp(-MAX_AIM_OFFSET, MAX_AIM_OFFSET))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        // Without enemies just reflect it straight back
+        .map_or(y, |(_, offset)| y + offset)
+}
+
+/// Move the target of the paddle center out of the lanes of the lasers, they can't be reflected.
+fn dodge(target_y: f64, half_height: f64, lasers: &[(f64, f64)]) -> f64 {
+    lasers.iter().fold(target_y, |y, (top, bottom)| {
+        let top = top - half_height - LASER_MARGIN;
+        let bottom = bottom + half_height + LASER_MARGIN;
+        if y <= top || y >= bottom {
+            return y;
+        }
+
+        // Leave the lane on the closest side that's still on the screen
+        let fits_above = top >= half_height;
+        let fits_below = bottom <= crate::HEIGHT as f64 - half_height;
+        if fits_above && (y - top < bottom - y || !fits_below) {
+            top
+        } else {
+            bottom
+        }
+    })
+}
+
+/// System that moves the paddles with the `Bot` controller.
+pub struct AutopilotSystem;
+impl<'a> System<'a> for AutopilotSystem {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Projectile>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, HoldProjectile>,
+        ReadStorage<'a, Laser>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Speed>,
+        ReadStorage<'a, BoundingBox>,
+    );
+
+    fn run(
+        &mut self,
+        (player, projectile, enemy, hold, laser, pos, mut vel, speed, bb): Self::SystemData,
+    ) {
+        let enemies = (&pos, &vel, &bb, &enemy)
+            .join()
+            .map(|(pos, vel, bb, _)| (pos.0 + bb.center_offset(), vel.0))
+            .collect::<Vec<_>>();
+
+        let lasers = (&pos, &bb, &laser)
+            .join()
+            .map(|(pos, bb, _)| (pos.y, pos.y + bb.y))
+            .collect::<Vec<_>>();
+
+        let bots = (&player, &pos, &bb)
+            .join()
+            .filter(|(player, _, _)| player.controller() == Controller::Bot)
+            .map(|(player, pos, bb)| (player.id(), pos.0, bb.0))
+            .collect::<Vec<_>>();
+
+        for (id, paddle_pos, paddle_size) in bots {
+            let paddle_x = paddle_pos.x + paddle_size.x;
+            let center_y = paddle_pos.y + paddle_size.y / 2.0;
+
+            let threats = (
+                &pos,
+                &vel,
+                &bb,
+                (&projectile).maybe(),
+                (&enemy).maybe(),
+                !&hold,
+            )
+                .join()
+                .filter(|(_, _, _, projectile, enemy, _)| projectile.is_some() || enemy.is_some())
+                .filter_map(|(pos, vel, bb, projectile, _, _)| {
+                    predict(pos.0, vel.0, bb.0, paddle_x).map(|(time, y)| Threat {
+                        time,
+                        y,
+                        projectile_speed: projectile.map(|_| vel.magnitude()),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            // Intercept the first threat that can still be reached, or the first one when it's
+            // impossible to reach any of them
+            let reachable = |threat: &&Threat| {
+                (threat.y - center_y).abs() <= threat.time * MAX_PADDLE_SPEED + paddle_size.y / 2.0
+            };
+            let target = threats
+                .iter()
+                .filter(reachable)
+                .min_by(|a, b| a.time.total_cmp(&b.time))
+                .or_else(|| threats.iter().min_by(|a, b| a.time.total_cmp(&b.time)));
+
+            let target_y = match target {
+                Some(Threat {
+                    y,
+                    projectile_speed: Some(speed),
+                    ..
+                }) => aim(*y, paddle_x, *speed, &enemies),
+                Some(threat) => threat.y,
+                // Wait in the middle of the screen
+                None => crate::HEIGHT as f64 / 2.0,
+            };
+            let target_y = dodge(target_y, paddle_size.y / 2.0, &lasers);
+
+            // Steer towards the target, accounting for the distance needed to brake
+            if let Some((_, vel, speed)) = (&player, &mut vel, &speed)
+                .join()
+                .find(|(player, _, _)| player.id() == id)
+            {
+                let error = target_y - center_y - vel.y * BRAKE_FRAMES;
+                vel.y += (error / SLOWDOWN_DISTANCE).clamp(-1.0, 1.0) * speed.0;
+            }
+        }
+    }
+}
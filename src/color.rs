@@ -1,5 +1,126 @@
 pub const FOREGROUND: u32 = 0xFFF3F7FB;
 pub const RED: u32 = 0xFF0000FF;
 pub const GREEN: u32 = 0xFF99FF99;
+pub const ORANGE: u32 = 0xFF0088FF;
 pub const STAR_BRIGHT: u32 = 0xFF888888;
 pub const STAR_DIM: u32 = 0xFF444444;
+
+/// Make a color darker, the amount is between 0 for black and 1 for the color itself.
+pub fn dim(color: u32, amount: f64) -> u32 {
+    (0..3).fold(0xFF000000, |dimmed, channel| {
+        let shift = channel * 8;
+        let value = ((color >> shift) & 0xFF) as f64 * amount;
+
+        dimmed | ((value as u32) << shift)
+    })
+}
+
+/// A set of colors made to be told apart by everyone.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Preset {
+    #[default]
+    Default,
+    /// Red and green are hard to tell apart, blue and orange are used instead.
+    Deuteranopia,
+    /// Red looks dark, bright orange and blue are used instead.
+    Protanopia,
+    HighContrast,
+}
+
+impl Preset {
+    /// The preset after this one, wraps around.
+    pub fn next(self) -> Self {
+        match self {
+            Preset::Default => Preset::Deuteranopia,
+            Preset::Deuteranopia => Preset::Protanopia,
+            Preset::Protanopia => Preset::HighContrast,
+            Preset::HighContrast => Preset::Default,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Deuteranopia => "deuteranopia",
+            Preset::Protanopia => "protanopia",
+            Preset::HighContrast => "high contrast",
+        }
+    }
+}
+
+/// The colors of everything that has a meaning, named after their role.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Palette {
+    preset: Preset,
+    /// Losing lives and the engines of the enemies.
+    pub danger: u32,
+    /// Gaining lives and protecting the planet.
+    pub heal: u32,
+    /// Shots and charged reflections of the paddles.
+    pub friendly_projectile: u32,
+    /// Trails of the enemy rockets.
+    pub enemy_projectile: u32,
+    /// Exploding enemies.
+    pub explosion: u32,
+    /// Flashes, trails and meters that are ready.
+    pub ui: u32,
+    /// Meters that aren't ready yet.
+    pub ui_inactive: u32,
+    /// The empty part of the meters and the end of the trails.
+    pub ui_background: u32,
+}
+
+impl Palette {
+    pub fn new(preset: Preset) -> Self {
+        let default = Self {
+            preset,
+            danger: RED,
+            heal: GREEN,
+            friendly_projectile: GREEN,
+            enemy_projectile: FOREGROUND,
+            explosion: ORANGE,
+            ui: FOREGROUND,
+            ui_inactive: STAR_BRIGHT,
+            ui_background: STAR_DIM,
+        };
+
+        match preset {
+            Preset::Default => default,
+            Preset::Deuteranopia => Self {
+                danger: 0xFF005ED5,
+                heal: 0xFFB27200,
+                friendly_projectile: 0xFFE9B456,
+                explosion: 0xFF009FE6,
+                ..default
+            },
+            Preset::Protanopia => Self {
+                danger: 0xFF009FE6,
+                heal: 0xFFB27200,
+                friendly_projectile: 0xFFE9B456,
+                explosion: 0xFF42E4F0,
+                ..default
+            },
+            Preset::HighContrast => Self {
+                danger: 0xFF0000FF,
+                heal: 0xFF00FFFF,
+                friendly_projectile: 0xFFFFFF00,
+                enemy_projectile: 0xFFFF00FF,
+                explosion: 0xFFFFFFFF,
+                ui: 0xFFFFFFFF,
+                ui_inactive: 0xFF999999,
+                ui_background: 0xFF333333,
+                ..default
+            },
+        }
+    }
+
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(Preset::default())
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{
+    color::{self, Palette},
+    gui::{self, Gui},
+    input::Input,
+    mask::Mask,
+    phase::Phase,
+    sprite,
+};
+use miniquad::KeyCode;
+use specs_blit::PixelBuffer;
+use sprite_gen::{MaskValue, Options};
+
+/// Area on the left side the mask grid is drawn in.
+const GRID_POS: (i32, i32) = (10, 20);
+const GRID_SIZE: (i32, i32) = (170, 210);
+/// Cells never get bigger than this, so small masks don't fill the whole area.
+const MAX_CELL_SIZE: i32 = 14;
+/// Masks can't get bigger than this so the cells stay visible.
+const MAX_MASK_SIZE: usize = 64;
+/// Position of the first brush, the others are next to it.
+const BRUSH_POS: (i32, i32) = (10, 240);
+const BRUSH_SIZE: i32 = 14;
+/// Position of the first slider, the others are below it.
+const SLIDER_POS: (i32, i32) = (195, 20);
+const SLIDER_SIZE: (i32, i32) = (190, 6);
+/// Vertical distance between the options.
+const OPTION_SPACING: i32 = 20;
+/// Position of the first toggle, the others are below it.
+const TOGGLE_POS: (i32, i32) = (195, 105);
+/// Area the generated variants are shown in.
+const VARIANTS_POS: (i32, i32) = (195, 165);
+const VARIANTS_SIZE: (i32, i32) = (190, 80);
+/// Amount of variants generated with different seeds.
+const VARIANTS: usize = 4;
+/// Pixels between the variants.
+const VARIANT_SPACING: usize = 4;
+/// Variants are enlarged at most this much.
+const MAX_VARIANT_SCALE: usize = 3;
+/// Color of the lines between the cells.
+const GRID_COLOR: u32 = 0xFF333333;
+/// Colors of the empty, solid and both kinds of body pixels in the grid.
+const CELL_COLORS: [u32; 4] = [0xFF000000, color::FOREGROUND, 0xFF888888, 0xFF4488DD];
+
+/// The value a brush paints, in the same order as the colors.
+const BRUSHES: [(MaskValue, &str); 4] = [
+    (MaskValue::Empty, "Empty"),
+    (MaskValue::Solid, "Solid"),
+    (MaskValue::Body1, "Body 1"),
+    (MaskValue::Body2, "Body 2"),
+];
+
+/// Gets an option out of the generator options.
+type Field<T> = fn(&mut Options) -> &mut T;
+
+/// Sliders for the options that are a fraction.
+const SLIDERS: [(&str, Field<f32>); 4] = [
+    ("Edge brightness", |options| &mut options.edge_brightness),
+    ("Color variations", |options| &mut options.color_variations),
+    ("Brightness noise", |options| &mut options.brightness_noise),
+    ("Saturation", |options| &mut options.saturation),
+];
+
+/// Checkboxes for the options that are on or off.
+const TOGGLES: [(&str, Field<bool>); 3] = [
+    ("Mirror X", |options| &mut options.mirror_x),
+    ("Mirror Y", |options| &mut options.mirror_y),
+    ("Colored", |options| &mut options.colored),
+];
+
+/// Paint a sprite mask with the mouse and see what's generated from it.
+pub struct Editor {
+    /// Name of the mask that's edited, it's saved under this name.
+    name: &'static str,
+    mask: Mask,
+    /// Index of the selected brush.
+    brush: usize,
+    /// The seed of the first variant, the others follow it.
+    seed: u64,
+    /// Width and pixels of the sprites generated from the mask.
+    variants: Vec<(usize, Vec<u32>)>,
+    /// Whether the mouse was down the previous frame, to detect clicks.
+    was_mouse_down: bool,
+    /// Shown at the bottom after saving.
+    status: String,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let name = Mask::names().next().expect("There are no masks");
+
+        let mut editor = Self {
+            name,
+            mask: Mask::load(name).expect("Could not load mask"),
+            brush: 2,
+            seed: 1,
+            variants: vec![],
+            was_mouse_down: false,
+            status: String::new(),
+        };
+        editor.generate();
+
+        editor
+    }
+
+    /// Handle the mouse and keyboard, switches back to the menu on escape.
+    pub fn update(&mut self, phase: &mut Phase, input: &Input) {
+        let clicked = input.mouse_down() && !self.was_mouse_down;
+        self.was_mouse_down = input.mouse_down();
+        let mouse = (input.mouse_x(), input.mouse_y());
+
+        let mut changed = false;
+
+        // Paint while the mouse is held
+        if input.mouse_down() {
+            if let Some((x, y)) = self.cell_at(mouse) {
+                let value = BRUSHES[self.brush].0.clone();
+                if *self.mask.pixel(x, y) != value {
+                    self.mask.set_pixel(x, y, value);
+                    changed = true;
+                }
+            }
+
+            for (index, (_, slider)) in SLIDERS.iter().enumerate() {
+                let (x, y) = slider_pos(index);
+                if inside(mouse, (x, y - 2), (SLIDER_SIZE.0, SLIDER_SIZE.1 + 4)) {
+                    let value = (mouse.0 - x) as f32 / (SLIDER_SIZE.0 - 1) as f32;
+                    *slider(self.mask.options_mut()) = value.clamp(0.0, 1.0);
+                    changed = true;
+                }
+            }
+        }
+
+        if clicked {
+            for index in 0..BRUSHES.len() {
+                if inside(mouse, brush_pos(index), (BRUSH_SIZE, BRUSH_SIZE)) {
+                    self.brush = index;
+                }
+            }
+
+            for (index, (_, toggle)) in TOGGLES.iter().enumerate() {
+                if inside(mouse, toggle_pos(index), (SLIDER_SIZE.0, 12)) {
+                    let value = toggle(self.mask.options_mut());
+                    *value = !*value;
+                    changed = true;
+                }
+            }
+        }
+
+        // Resize the mask with the arrow keys
+        let (width, height) = (self.mask.width(), self.mask.height());
+        for (key, new_width, new_height) in [
+            (KeyCode::Left, width.saturating_sub(1), height),
+            (KeyCode::Right, (width + 1).min(MAX_MASK_SIZE), height),
+            (KeyCode::Up, width, height.saturating_sub(1)),
+            (KeyCode::Down, width, (height + 1).min(MAX_MASK_SIZE)),
+        ] {
+            if input.key_pressed(key) {
+                self.mask.resize(new_width, new_height);
+                changed = true;
+            }
+        }
+
+        if input.key_pressed(KeyCode::Tab) {
+            self.load_next();
+            changed = true;
+        }
+        if input.key_pressed(KeyCode::R) {
+            self.seed += VARIANTS as u64;
+            changed = true;
+        }
+        if input.key_pressed(KeyCode::S) {
+            // The masks are embedded, so the game only uses it after building it again
+            self.status = match self.mask.save(self.name) {
+                Ok(_) => format!("Saved {}, rebuild to use it", self.name),
+                Err(err) => format!("Could not save: {}", err),
+            };
+        }
+        if input.key_pressed(KeyCode::Escape) {
+            *phase = Phase::SwitchTo(Box::new(Phase::Menu));
+        }
+
+        if changed {
+            self.generate();
+        }
+    }
+
+    pub fn render(&self, buffer: &mut PixelBuffer, gui: &mut Gui, palette: &Palette) {
+        gui.draw_label(
+            buffer,
+            format!("{} {}x{}", self.name, self.mask.width(), self.mask.height()),
+            GRID_POS.0,
+            5,
+        );
+
+        // The mask itself
+        let cell = self.cell_size();
+        for y in 0..self.mask.height() {
+            for x in 0..self.mask.width() {
+                let color = CELL_COLORS[brush_index(self.mask.pixel(x, y))];
+                let pos = (GRID_POS.0 + x as i32 * cell, GRID_POS.1 + y as i32 * cell);
+                fill(buffer, pos, (cell, cell), GRID_COLOR);
+                fill(buffer, (pos.0 + 1, pos.1 + 1), (cell - 1, cell - 1), color);
+            }
+        }
+
+        for (index, (_, name)) in BRUSHES.iter().enumerate() {
+            let (x, y) = brush_pos(index);
+            let outline = if index == self.brush {
+                palette.ui
+            } else {
+                palette.ui_inactive
+            };
+            fill(buffer, (x, y), (BRUSH_SIZE, BRUSH_SIZE), outline);
+            fill(
+                buffer,
+                (x + 2, y + 2),
+                (BRUSH_SIZE - 4, BRUSH_SIZE - 4),
+                CELL_COLORS[index],
+            );
+
+            if index == self.brush {
+                let (x, _) = brush_pos(BRUSHES.len());
+                gui.draw_label(buffer, *name, x + 4, BRUSH_POS.1 + 3);
+            }
+        }
+
+        let mut options = *self.mask.options();
+        for (index, (name, slider)) in SLIDERS.iter().enumerate() {
+            let (x, y) = slider_pos(index);
+            let value = *slider(&mut options);
+            gui.draw_label(buffer, format!("{} {:.2}", name, value), x, y - 11);
+            gui::draw_meter(
+                buffer,
+                palette,
+                (x as usize, y as usize),
+                (SLIDER_SIZE.0 as usize, SLIDER_SIZE.1 as usize),
+                value as f64,
+                palette.ui,
+            );
+        }
+
+        for (index, (name, toggle)) in TOGGLES.iter().enumerate() {
+            let (x, y) = toggle_pos(index);
+            let check = if *toggle(&mut options) { "x" } else { " " };
+            gui.draw_label(buffer, format!("[{}] {}", check, name), x, y);
+        }
+
+        // The sprites generated with different seeds, next to each other as far as they fit
+        if let Some((width, pixels)) = self.variants.first() {
+            let height = pixels.len() / width;
+            let slot = VARIANTS_SIZE.0 as usize / VARIANTS;
+            let scale = ((slot - VARIANT_SPACING) / width)
+                .min(VARIANTS_SIZE.1 as usize / height)
+                .clamp(1, MAX_VARIANT_SCALE);
+
+            let fits = if height * scale <= VARIANTS_SIZE.1 as usize {
+                (VARIANTS_SIZE.0 as usize + VARIANT_SPACING) / (width * scale + VARIANT_SPACING)
+            } else {
+                0
+            };
+            if fits == 0 {
+                gui.draw_label(buffer, "Too big to preview", VARIANTS_POS.0, VARIANTS_POS.1);
+            }
+
+            for (index, (width, pixels)) in self.variants.iter().take(fits).enumerate() {
+                sprite::draw_scaled(
+                    buffer,
+                    (
+                        VARIANTS_POS.0 as usize + index * (width * scale + VARIANT_SPACING),
+                        VARIANTS_POS.1 as usize,
+                    ),
+                    scale,
+                    *width,
+                    pixels,
+                );
+            }
+        }
+
+        gui.draw_label(
+            buffer,
+            "Arrows resize, Tab next mask, R reroll\nS save, Esc back to the menu",
+            GRID_POS.0,
+            272,
+        );
+        gui.draw_label(
+            buffer,
+            self.status.as_str(),
+            BRUSH_POS.0,
+            BRUSH_POS.1 + BRUSH_SIZE + 4,
+        );
+    }
+
+    /// Switch to the next embedded mask, unsaved changes are lost.
+    fn load_next(&mut self) {
+        let name = Mask::names()
+            .skip_while(|name| *name != self.name)
+            .nth(1)
+            .or_else(|| Mask::names().next())
+            .expect("There are no masks");
+
+        self.name = name;
+        self.mask = Mask::load(name).expect("Could not load mask");
+        self.status = String::new();
+    }
+
+    /// Generate the variants again after the mask changed.
+    fn generate(&mut self) {
+        self.variants = (0..VARIANTS as u64)
+            .map(|index| {
+                let buffer = self.mask.buffer_with_seed(self.seed + index);
+
+                (buffer.width() as usize, buffer.to_raw_buffer())
+            })
+            .collect();
+    }
+
+    /// Size of the cells so the whole mask fits in the grid area.
+    fn cell_size(&self) -> i32 {
+        (GRID_SIZE.0 / self.mask.width() as i32)
+            .min(GRID_SIZE.1 / self.mask.height() as i32)
+            .clamp(2, MAX_CELL_SIZE)
+    }
+
+    /// The pixel of the mask under the mouse.
+    fn cell_at(&self, (x, y): (i32, i32)) -> Option<(usize, usize)> {
+        let cell = self.cell_size();
+        let (x, y) = (x - GRID_POS.0, y - GRID_POS.1);
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        let (x, y) = ((x / cell) as usize, (y / cell) as usize);
+        if x < self.mask.width() && y < self.mask.height() {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+}
+
+fn brush_pos(index: usize) -> (i32, i32) {
+    (BRUSH_POS.0 + index as i32 * (BRUSH_SIZE + 4), BRUSH_POS.1)
+}
+
+fn slider_pos(index: usize) -> (i32, i32) {
+    (SLIDER_POS.0, SLIDER_POS.1 + index as i32 * OPTION_SPACING)
+}
+
+fn toggle_pos(index: usize) -> (i32, i32) {
+    (
+        TOGGLE_POS.0,
+        TOGGLE_POS.1 + index as i32 * (OPTION_SPACING - 5),
+    )
+}
+
+/// The brush that paints this value.
+fn brush_index(value: &MaskValue) -> usize {
+    BRUSHES
+        .iter()
+        .position(|(brush, _)| brush == value)
+        .unwrap_or(0)
+}
+
+fn inside((x, y): (i32, i32), (left, top): (i32, i32), (width, height): (i32, i32)) -> bool {
+    x >= left && x < left + width && y >= top && y < top + height
+}
+
+/// Fill a rectangle with a color.
+fn fill(buffer: &mut PixelBuffer, (x, y): (i32, i32), (width, height): (i32, i32), color: u32) {
+    let (buffer_width, buffer_height) = (buffer.width() as i32, buffer.height() as i32);
+    let pixels = buffer.pixels_mut();
+
+    for fill_y in y.max(0)..(y + height).min(buffer_height) {
+        for fill_x in x.max(0)..(x + width).min(buffer_width) {
+            pixels[(fill_y * buffer_width + fill_x) as usize] = color;
+        }
+    }
+}
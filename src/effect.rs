@@ -3,6 +3,13 @@ use specs_blit::{
     PixelBuffer,
 };
 
+/// Trauma added to the camera when a life is lost.
+pub const LIFE_LOST_SHAKE: f64 = 0.6;
+/// Trauma removed every frame.
+const TRAUMA_DECAY: f64 = 0.02;
+/// Pixels the camera moves away at full trauma.
+const MAX_SHAKE: f64 = 8.0;
+
 /// A particle that moves around but doesn't collide.
 #[derive(Component, Debug, Default)]
 pub struct ScreenFlash(u32);
@@ -14,6 +21,10 @@ impl ScreenFlash {
     pub fn new(color: u32) -> Self {
         Self(color)
     }
+
+    pub fn color(&self) -> u32 {
+        self.0
+    }
 }
 
 /// System that will flash the screen.
@@ -27,3 +38,52 @@ impl<'a> System<'a> for ScreenFlashSystem {
         }
     }
 }
+
+/// The offset everything is drawn with, shakes when the screen is hit hard.
+///
+/// Losing a life and the explosions of the bigger ships shake it.
+#[derive(Debug, Default)]
+pub struct Camera {
+    /// How hard the screen shakes, between 0 and 1.
+    trauma: f64,
+    /// Frames the camera has been shaking, used to pick the offset.
+    time: f64,
+    offset: (i32, i32),
+}
+
+impl Camera {
+    /// Shake the screen, multiple shakes add up.
+    pub fn shake(&mut self, trauma: f64) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+
+    /// The offset in pixels to draw everything with.
+    pub fn offset(&self) -> (i32, i32) {
+        self.offset
+    }
+}
+
+/// Shake the screen with this trauma when the entity is destroyed.
+#[derive(Component, Debug, Default)]
+pub struct ShakeOnDeath(pub f64);
+
+/// System that lets the camera shake calm down.
+pub struct CameraSystem;
+impl<'a> System<'a> for CameraSystem {
+    type SystemData = Write<'a, Camera>;
+
+    fn run(&mut self, mut camera: Self::SystemData) {
+        if camera.trauma <= 0.0 {
+            camera.offset = (0, 0);
+            return;
+        }
+
+        // Use waves instead of random so the simulation isn't affected
+        camera.time += 1.0;
+        let shake = camera.trauma * camera.trauma * MAX_SHAKE;
+        let (x, y) = ((camera.time * 1.3).sin(), (camera.time * 1.7 + 0.5).sin());
+        camera.offset = ((x * shake).round() as i32, (y * shake).round() as i32);
+
+        camera.trauma = (camera.trauma - TRAUMA_DECAY).max(0.0);
+    }
+}
@@ -1,29 +1,45 @@
 use crate::{
-    color,
-    effect::ScreenFlash,
+    color::Palette,
+    effect::{Camera, ScreenFlash, ShakeOnDeath, LIFE_LOST_SHAKE},
     entity::Lifetime,
+    formation::{Follower, Formation},
     lives::Lives,
     money::{Money, Wallet},
     movement::*,
     particle::ParticleEmitter,
+    pattern::{Pattern, Target},
     phase::Phase,
     physics::*,
     player::Player,
-    projectile::{Projectile, ProjectileEmitter},
+    projectile::{Damage, Projectile, ProjectileEmitter, ReflectedBy},
     random,
+    score::Scores,
+    script::{Script, Scripts},
     ship::Ships,
-    sprite::{RotationFollowsVelocity, Sprites},
+    sprite::{Animation, RotationFollowsVelocity, SpriteRef, Sprites, EXPLOSION_SIZE},
 };
 use derive_deref::{Deref, DerefMut};
-use specs_blit::{specs::*, Sprite, SpriteRef};
+use specs_blit::specs::*;
+
+type Vec2 = vek::Vec2<f64>;
 
 const ENEMY_ENGINE_PARTICLE_LIFETIME: f64 = 10.0;
+/// Frames of the engine flicker shown every second.
+const ENGINE_FRAME_RATE: f64 = 15.0;
+/// Frames of the explosion sequence shown every second.
+const EXPLOSION_FRAME_RATE: f64 = 20.0;
 const ENEMY_DEAD_EMITTER_LIFETIME: f64 = 5.0;
-const ENEMY_DEAD_PARTICLE_LIFETIME: f64 = 10.0;
+const ENEMY_DEAD_PARTICLE_LIFETIME: f64 = 20.0;
 
 const TIME_RANDOM_FACTOR: f64 = 10.0;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// The first level where enemies can fly in formation.
+const FORMATION_MIN_LEVEL: usize = 5;
+/// The chance a wave of small or medium enemies is a formation.
+const FORMATION_CHANCE: f64 = 0.25;
+const FORMATION_MAX_FOLLOWERS: usize = 6;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum EnemyType {
     Small,
     Medium,
@@ -31,6 +47,19 @@ pub enum EnemyType {
 }
 
 impl EnemyType {
+    pub fn all() -> &'static [EnemyType] {
+        &[EnemyType::Small, EnemyType::Medium, EnemyType::Big]
+    }
+
+    /// Name used for the script files.
+    pub fn name(self) -> &'static str {
+        match self {
+            EnemyType::Small => "small",
+            EnemyType::Medium => "medium",
+            EnemyType::Big => "big",
+        }
+    }
+
     pub fn random() -> Self {
         if random::range(0.0, 1.0) < 0.1 {
             EnemyType::Big
@@ -49,8 +78,9 @@ impl EnemyType {
         }
     }
 
-    pub fn sprite(self, ships: &Ships) -> SpriteRef {
-        ships.enemy(self)
+    /// The flickering engine animation of a random ship variation.
+    pub fn animation(self, ships: &Ships) -> Animation {
+        Animation::new(ships.enemy(self), ENGINE_FRAME_RATE).with_random_start()
     }
 
     pub fn projectile_sprite(self, sprites: &Sprites) -> (SpriteRef, f64, f64) {
@@ -92,6 +122,45 @@ impl EnemyType {
         }
     }
 
+    /// The bullet patterns this archetype can spawn with.
+    pub fn shoot_patterns(self) -> &'static [Pattern] {
+        match self {
+            EnemyType::Small => &[
+                Pattern::Single,
+                Pattern::Single,
+                Pattern::Aimed(Target::Paddle),
+                Pattern::Burst {
+                    shots: 3,
+                    delay: 8.0,
+                },
+            ],
+            EnemyType::Medium => &[
+                Pattern::Single,
+                Pattern::Aimed(Target::Planet),
+                Pattern::Fan {
+                    amount: 3,
+                    angle: 0.5,
+                },
+                Pattern::Burst {
+                    shots: 3,
+                    delay: 10.0,
+                },
+                Pattern::Missile { fuel: 180.0 },
+            ],
+            EnemyType::Big => &[
+                Pattern::Single,
+                Pattern::Ring { amount: 3 },
+                Pattern::Spiral { arms: 3, step: 0.3 },
+                Pattern::Mine { delay: 90.0 },
+                Pattern::Laser {
+                    warmup: 60.0,
+                    duration: 90.0,
+                    sweep: 0.5,
+                },
+            ],
+        }
+    }
+
     pub fn shoot_split_into(self, sprites: &Sprites) -> Option<SpriteRef> {
         match self {
             EnemyType::Small => None,
@@ -107,11 +176,56 @@ impl EnemyType {
         }) as usize
     }
 
-    pub fn particle_amount(self) -> u8 {
+    /// How hard the screen shakes when it explodes.
+    pub fn shake(self) -> f64 {
         match self {
-            EnemyType::Small => 1,
-            EnemyType::Medium => 4,
-            EnemyType::Big => 12,
+            EnemyType::Small => 0.0,
+            EnemyType::Medium => 0.2,
+            EnemyType::Big => 0.5,
+        }
+    }
+
+    /// Engine particles emitted every second.
+    pub fn particle_rate(self) -> f64 {
+        match self {
+            EnemyType::Small => 60.0,
+            EnemyType::Medium => 240.0,
+            EnemyType::Big => 720.0,
+        }
+    }
+
+    /// How much energy the commander in versus mode needs to deploy this type.
+    pub fn energy_cost(self) -> f64 {
+        match self {
+            EnemyType::Small => 10.0,
+            EnemyType::Medium => 30.0,
+            EnemyType::Big => 60.0,
+        }
+    }
+
+    /// The movement patterns this archetype can spawn with.
+    pub fn movements(self) -> &'static [Movement] {
+        match self {
+            EnemyType::Small => &[
+                Movement::Straight,
+                Movement::Zigzag,
+                Movement::Homing,
+                Movement::Dive,
+                Movement::Path,
+            ],
+            EnemyType::Medium => &[
+                Movement::Straight,
+                Movement::Zigzag,
+                Movement::Circling,
+                Movement::StopAndGo,
+                Movement::Retreat,
+            ],
+            EnemyType::Big => &[
+                Movement::Straight,
+                Movement::Zigzag,
+                Movement::StopAndGo,
+                Movement::Retreat,
+            ],
         }
     }
 
@@ -132,10 +246,53 @@ impl EnemyType {
     }
 }
 
+/// Enemies that spawn at the same time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Wave {
+    Single(EnemyType),
+    /// A leader with an amount of followers.
+    Formation(Formation, EnemyType, usize),
+}
+
+impl Wave {
+    /// A random wave, formations only appear in the later levels.
+    pub fn random(level: usize) -> Self {
+        let type_ = EnemyType::random();
+        if type_ == EnemyType::Big
+            || level < FORMATION_MIN_LEVEL
+            || random::range(0.0, 1.0) > FORMATION_CHANCE
+        {
+            return Wave::Single(type_);
+        }
+
+        let followers = 2 + random::usize((level - FORMATION_MIN_LEVEL) / 2 + 1);
+
+        Wave::Formation(
+            Formation::random(),
+            type_,
+            followers.min(FORMATION_MAX_FOLLOWERS),
+        )
+    }
+
+    pub fn type_(self) -> EnemyType {
+        match self {
+            Wave::Single(type_) | Wave::Formation(_, type_, _) => type_,
+        }
+    }
+
+    /// Amount of enemies in the wave.
+    pub fn amount(self) -> usize {
+        match self {
+            Wave::Single(_) => 1,
+            Wave::Formation(_, _, followers) => followers + 1,
+        }
+    }
+}
+
 #[derive(Component, Debug, Default)]
 pub struct EnemyEmitter {
     /// Time, resources for enemy.
-    spawner: Vec<(f64, EnemyType)>,
+    spawner: Vec<(f64, Wave)>,
     current_time: f64,
     total_time: f64,
 }
@@ -146,10 +303,10 @@ impl EnemyEmitter {
             // Big ship is spawning these
             return Self {
                 spawner: vec![
-                    (2.0 * 60.0, EnemyType::Small),
-                    (3.5 * 60.0, EnemyType::Small),
-                    (5.0 * 60.0, EnemyType::Small),
-                    (7.0 * 60.0, EnemyType::Small),
+                    (2.0 * 60.0, Wave::Single(EnemyType::Small)),
+                    (3.5 * 60.0, Wave::Single(EnemyType::Small)),
+                    (5.0 * 60.0, Wave::Single(EnemyType::Small)),
+                    (7.0 * 60.0, Wave::Single(EnemyType::Small)),
                 ],
                 current_time: 0.0,
                 total_time: 10.0 * 60.0,
@@ -160,28 +317,31 @@ impl EnemyEmitter {
         match level {
             1 => {
                 return Self {
-                    spawner: vec![(30.0, EnemyType::Small)],
+                    spawner: vec![(30.0, Wave::Single(EnemyType::Small))],
                     current_time: 0.0,
                     total_time: 5.0 * 60.0,
                 };
             }
             2 => {
                 return Self {
-                    spawner: vec![(30.0, EnemyType::Small), (120.0, EnemyType::Small)],
+                    spawner: vec![
+                        (30.0, Wave::Single(EnemyType::Small)),
+                        (120.0, Wave::Single(EnemyType::Small)),
+                    ],
                     current_time: 0.0,
                     total_time: 5.0 * 60.0,
                 };
             }
             3 => {
                 return Self {
-                    spawner: vec![(30.0, EnemyType::Medium)],
+                    spawner: vec![(30.0, Wave::Single(EnemyType::Medium))],
                     current_time: 0.0,
                     total_time: 5.0 * 60.0,
                 };
             }
             4 => {
                 return Self {
-                    spawner: vec![(30.0, EnemyType::Big)],
+                    spawner: vec![(30.0, Wave::Single(EnemyType::Big))],
                     current_time: 0.0,
                     total_time: 5.0 * 60.0,
                 };
@@ -197,14 +357,15 @@ impl EnemyEmitter {
         let mut rest = 0.0;
         let mut spawner = (0..amount_of_enemies)
             .map(|index| {
-                let type_ = EnemyType::random();
+                let wave = Wave::random(level);
+                let type_ = wave.type_();
                 rest += type_.spawn_rest_before();
 
                 let result = (
                     index as f64
                         * (time_dist + random::range(-TIME_RANDOM_FACTOR, TIME_RANDOM_FACTOR))
                         + rest,
-                    type_,
+                    wave,
                 );
 
                 rest += type_.spawn_rest_after();
@@ -214,7 +375,7 @@ impl EnemyEmitter {
             .collect::<Vec<_>>();
 
         // Always spawn the first one immediately
-        if let Some(mut first) = spawner.first_mut() {
+        if let Some(first) = spawner.first_mut() {
             first.0 = 30.0;
         }
 
@@ -225,21 +386,55 @@ impl EnemyEmitter {
         }
     }
 
+    /// An emitter that doesn't spawn anything by itself but keeps the level running for some time.
+    ///
+    /// Used when the enemies are deployed by another player.
+    pub fn timer(total_time: f64) -> Self {
+        Self {
+            spawner: vec![],
+            current_time: 0.0,
+            total_time,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_enemy_with_resource_usage(
         entities: &Entities,
         updater: &LazyUpdate,
         sprites: &Sprites,
         ships: &Ships,
+        scripts: &Scripts,
+        palette: &Palette,
         type_: EnemyType,
         pos: &Option<&Position>,
-    ) {
-        let enemy = entities.create();
-        updater.insert(enemy, Enemy);
+    ) -> Entity {
+        let enemy = EnemyEmitter::spawn_enemy(
+            entities, updater, sprites, ships, scripts, palette, type_, pos,
+        );
 
         if type_ == EnemyType::Big {
             updater.insert(enemy, EnemyEmitter::new(None));
         }
 
+        enemy
+    }
+
+    /// Spawn an enemy without the small ships a big ship launches, used when every ship has to
+    /// be paid for.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn_enemy(
+        entities: &Entities,
+        updater: &LazyUpdate,
+        sprites: &Sprites,
+        ships: &Ships,
+        scripts: &Scripts,
+        palette: &Palette,
+        type_: EnemyType,
+        pos: &Option<&Position>,
+    ) -> Entity {
+        let enemy = entities.create();
+        updater.insert(enemy, Enemy);
+
         let bb = type_.bb();
 
         updater.insert(
@@ -253,34 +448,37 @@ impl EnemyEmitter {
             },
         );
 
-        updater.insert(enemy, Sprite::new(type_.sprite(&ships)));
+        let animation = type_.animation(ships);
+        updater.insert(enemy, animation.first().sprite());
+        updater.insert(enemy, animation.first().id());
+        updater.insert(enemy, animation);
         updater.insert(enemy, RotationFollowsVelocity);
 
         let speed_x = type_.speed_x();
         let speed_y = type_.speed_y();
 
-        if random::bool() {
-            // Straight pattern
+        if scripts.has(type_) {
+            // The script decides how to move
             updater.insert(enemy, Velocity::new(-speed_x, speed_y));
+            updater.insert(enemy, Script::new(type_));
         } else {
-            // Zigzag pattern
-            updater.insert(enemy, Velocity::new(-speed_x, 0.0));
-            updater.insert(enemy, Zigzag::new(speed_y, random::range(0.001, 0.2)));
+            random::index(type_.movements()).insert(enemy, updater, speed_x, speed_y);
         }
         if speed_x > 0.7 {
             updater.insert(
                 enemy,
-                ParticleEmitter::new(
-                    ENEMY_ENGINE_PARTICLE_LIFETIME,
-                    sprites.white_particle.clone(),
-                )
-                .with_amount(type_.particle_amount())
-                .with_dispersion(speed_x * 0.2)
-                .with_offset(bb.center_offset()),
+                ParticleEmitter::new(ENEMY_ENGINE_PARTICLE_LIFETIME, palette.explosion)
+                    .with_rate(type_.particle_rate())
+                    .with_gradient(palette.explosion, palette.danger)
+                    .with_fade()
+                    .with_lifetime_jitter(0.3)
+                    .with_dispersion(speed_x * 0.2)
+                    .with_offset(bb.center_offset()),
             );
         }
 
-        let (proj_sprite, proj_width, proj_height) = type_.projectile_sprite(&sprites);
+        let (proj_sprite, proj_width, proj_height) = type_.projectile_sprite(sprites);
+        let pattern = *random::index(type_.shoot_patterns());
 
         // Shoot bullets
         updater.insert(
@@ -288,19 +486,92 @@ impl EnemyEmitter {
             ProjectileEmitter::new(proj_sprite, BoundingBox::new(proj_width, proj_height))
                 .with_speed(speed_x + 2.0)
                 .with_spread(type_.shoot_spread())
-                .with_interval(type_.shoot_interval())
+                .with_interval(type_.shoot_interval() * pattern.interval_factor())
+                .with_pattern(pattern)
                 .with_offset(bb.center_offset())
-                .split_into(type_.shoot_split_into(&sprites)),
+                .split_into(type_.shoot_split_into(sprites)),
         );
 
         updater.insert(enemy, bb);
 
         // The rest of the resources is the leftover money
         updater.insert(enemy, Money::new(type_.money()));
+
+        if type_.shake() > 0.0 {
+            updater.insert(enemy, ShakeOnDeath(type_.shake()));
+        }
+
+        enemy
+    }
+
+    /// Spawn a single enemy or a whole formation.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn_wave(
+        entities: &Entities,
+        updater: &LazyUpdate,
+        sprites: &Sprites,
+        ships: &Ships,
+        scripts: &Scripts,
+        palette: &Palette,
+        wave: Wave,
+        pos: &Option<&Position>,
+    ) {
+        let (formation, type_, followers) = match wave {
+            Wave::Single(type_) => {
+                EnemyEmitter::spawn_enemy_with_resource_usage(
+                    entities, updater, sprites, ships, scripts, palette, type_, pos,
+                );
+
+                return;
+            }
+            Wave::Formation(formation, type_, followers) => (formation, type_, followers),
+        };
+
+        let offsets = formation.offsets(followers);
+
+        // Place the leader so the whole formation fits on the screen
+        let leader_pos = match pos {
+            Some(pos) => (*pos).clone(),
+            None => {
+                let bb = type_.bb();
+                let max_x = offsets.iter().map(|offset| offset.x).fold(0.0, f64::max);
+                let min_y = offsets.iter().map(|offset| offset.y).fold(0.0, f64::min);
+                let max_y = offsets.iter().map(|offset| offset.y).fold(0.0, f64::max);
+
+                Position::new(
+                    crate::WIDTH as f64 - 10.0 - max_x,
+                    random::range(-min_y, crate::HEIGHT as f64 - bb.y - max_y),
+                )
+            }
+        };
+
+        let leader = EnemyEmitter::spawn_enemy_with_resource_usage(
+            entities,
+            updater,
+            sprites,
+            ships,
+            scripts,
+            palette,
+            type_,
+            &Some(&leader_pos),
+        );
+        for offset in offsets {
+            let follower = EnemyEmitter::spawn_enemy_with_resource_usage(
+                entities,
+                updater,
+                sprites,
+                ships,
+                scripts,
+                palette,
+                type_,
+                &Some(&leader_pos.add_offset(offset)),
+            );
+            updater.insert(follower, Follower::new(leader, offset));
+        }
     }
 
     pub fn enemies_left(&self) -> usize {
-        self.spawner.len()
+        self.spawner.iter().map(|(_, wave)| wave.amount()).sum()
     }
 }
 
@@ -316,20 +587,27 @@ impl<'a> System<'a> for EnemySystem {
     type SystemData = (
         Entities<'a>,
         Write<'a, Phase>,
+        Write<'a, Camera>,
+        Read<'a, Palette>,
         Option<Write<'a, Lives>>,
         ReadStorage<'a, Enemy>,
         ReadStorage<'a, Position>,
+        ReadStorage<'a, BoundingBox>,
         Read<'a, LazyUpdate>,
     );
 
-    fn run(&mut self, (entities, mut phase, lives, enemy, pos, updater): Self::SystemData) {
+    fn run(
+        &mut self,
+        (entities, mut phase, mut camera, palette, lives, enemy, pos, bb, updater): Self::SystemData,
+    ) {
         if let Some(mut lives) = lives {
-            for (entity, pos, _) in (&*entities, &pos, &enemy).join() {
+            for (entity, pos, bb, _) in (&*entities, &pos, &bb, &enemy).join() {
                 if pos.0.x <= 0.0 {
-                    lives.reduce();
+                    lives.hit(pos.y + bb.y / 2.0);
+                    camera.shake(LIFE_LOST_SHAKE);
 
                     let flash = entities.create();
-                    updater.insert(flash, ScreenFlash::new(color::RED));
+                    updater.insert(flash, ScreenFlash::new(palette.danger));
                     updater.insert(flash, Lifetime::new(5.0));
 
                     let _ = entities.delete(entity);
@@ -343,31 +621,88 @@ impl<'a> System<'a> for EnemySystem {
     }
 }
 
+/// The particles of an exploding enemy.
+fn explosion(amount: usize, palette: &Palette) -> ParticleEmitter {
+    ParticleEmitter::new(ENEMY_DEAD_PARTICLE_LIFETIME, palette.ui)
+        .with_burst(amount)
+        .with_gradient(palette.ui, palette.explosion)
+        .with_fade()
+        .with_size(2.0, 1.0)
+        .with_dispersion(3.0)
+        .with_drag(0.9)
+        .with_lifetime_jitter(0.5)
+}
+
+/// Show the sequence of a ship exploding around a point.
+fn fireball(entities: &Entities, updater: &LazyUpdate, sprites: &Sprites, center: Vec2) {
+    let animation = Animation::new(sprites.explosion.clone(), EXPLOSION_FRAME_RATE).with_once();
+
+    let fireball = entities.create();
+    updater.insert(fireball, animation.first().sprite());
+    updater.insert(fireball, animation.first().id());
+    updater.insert(fireball, animation);
+    updater.insert(
+        fireball,
+        Position::from_vec2(center - Vec2::broadcast(EXPLOSION_SIZE as f64 / 2.0)),
+    );
+}
+
 pub struct EnemyCollisionSystem;
 impl<'a> System<'a> for EnemyCollisionSystem {
     type SystemData = (
         Entities<'a>,
-        Write<'a, Wallet>,
         ReadExpect<'a, Sprites>,
+        Write<'a, Wallet>,
+        Write<'a, Scores>,
+        Write<'a, Camera>,
+        Read<'a, Palette>,
         ReadStorage<'a, Enemy>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, Projectile>,
+        ReadStorage<'a, ReflectedBy>,
+        WriteStorage<'a, Damage>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Velocity>,
         ReadStorage<'a, BoundingBox>,
         ReadStorage<'a, Money>,
+        ReadStorage<'a, ShakeOnDeath>,
         Read<'a, LazyUpdate>,
     );
 
     fn run(
         &mut self,
-        (entities, mut wallet, sprites, enemy, player, projectile, pos, vel, bb, money, updater): Self::SystemData,
+        (
+            entities,
+            sprites,
+            mut wallet,
+            mut scores,
+            mut camera,
+            palette,
+            enemy,
+            player,
+            projectile,
+            reflected_by,
+            mut damage,
+            pos,
+            vel,
+            bb,
+            money,
+            shake,
+            updater,
+        ): Self::SystemData,
     ) {
         // Check for collision with the player
-        for (player_pos, player_bb, _) in (&pos, &bb, &player).join() {
+        for (player_pos, player_bb, player) in (&pos, &bb, &player).join() {
             let player_aabr = player_bb.to_aabr(player_pos);
-            for (entity, enemy_pos, enemy_bb, money, _) in
-                (&*entities, &pos, &bb, (&money).maybe(), &enemy).join()
+            for (entity, enemy_pos, enemy_bb, money, shake, _) in (
+                &*entities,
+                &pos,
+                &bb,
+                (&money).maybe(),
+                (&shake).maybe(),
+                &enemy,
+            )
+                .join()
             {
                 let enemy_aabr = enemy_bb.to_aabr(enemy_pos);
 
@@ -377,54 +712,72 @@ impl<'a> System<'a> for EnemyCollisionSystem {
 
                     if let Some(money) = money {
                         wallet.add(money);
+                        scores.add(player.id(), money.amount());
+                    }
+
+                    if let Some(shake) = shake {
+                        camera.shake(shake.0);
                     }
 
+                    fireball(&entities, &updater, &sprites, enemy_aabr.center());
+
                     let emitter = entities.create();
-                    updater.insert(
-                        emitter,
-                        ParticleEmitter::new(
-                            ENEMY_DEAD_PARTICLE_LIFETIME,
-                            sprites.white_particle.clone(),
-                        )
-                        .with_dispersion(3.0)
-                        .with_amount(8),
-                    );
-                    updater.insert(emitter, Position::from_vec2(enemy_aabr.center()));
+                    updater.insert(emitter, explosion(40, &palette));
                     updater.insert(emitter, Position::from_vec2(enemy_aabr.center()));
                     updater.insert(emitter, Lifetime::new(ENEMY_DEAD_EMITTER_LIFETIME));
                 }
             }
         }
         // Check for collision with the projectile
-        for (projectile_entity, projectile_pos, projectile_bb, projectile_vel, _) in
-            (&*entities, &pos, &bb, &vel, &projectile).join()
+        for (projectile_entity, projectile_pos, projectile_bb, projectile_vel, reflected_by, _) in (
+            &*entities,
+            &pos,
+            &bb,
+            &vel,
+            (&reflected_by).maybe(),
+            &projectile,
+        )
+            .join()
         {
             if projectile_vel.x > 0.0 {
                 let projectile_aabr = projectile_bb.to_aabr(projectile_pos);
-                for (enemy_entity, enemy_pos, enemy_bb, money, _) in
-                    (&*entities, &pos, &bb, (&money).maybe(), &enemy).join()
+                for (enemy_entity, enemy_pos, enemy_bb, money, shake, _) in (
+                    &*entities,
+                    &pos,
+                    &bb,
+                    (&money).maybe(),
+                    (&shake).maybe(),
+                    &enemy,
+                )
+                    .join()
                 {
                     let enemy_aabr = enemy_bb.to_aabr(enemy_pos);
 
                     if enemy_aabr.collides_with_aabr(projectile_aabr) {
-                        let _ = entities.delete(projectile_entity);
+                        // Charged projectiles go through multiple enemies
+                        match damage.get_mut(projectile_entity) {
+                            Some(damage) if damage.0 > 1 => damage.0 -= 1,
+                            _ => {
+                                let _ = entities.delete(projectile_entity);
+                            }
+                        }
                         let _ = entities.delete(enemy_entity);
 
                         if let Some(money) = money {
                             wallet.add(money);
+                            if let Some(reflected_by) = reflected_by {
+                                scores.add(reflected_by.0, money.amount());
+                            }
+                        }
+
+                        if let Some(shake) = shake {
+                            camera.shake(shake.0);
                         }
 
+                        fireball(&entities, &updater, &sprites, enemy_aabr.center());
+
                         let emitter = entities.create();
-                        updater.insert(
-                            emitter,
-                            ParticleEmitter::new(
-                                ENEMY_DEAD_PARTICLE_LIFETIME,
-                                sprites.white_particle.clone(),
-                            )
-                            .with_dispersion(3.0)
-                            .with_amount(4),
-                        );
-                        updater.insert(emitter, Position::from_vec2(enemy_aabr.center()));
+                        updater.insert(emitter, explosion(20, &palette));
                         updater.insert(emitter, Position::from_vec2(enemy_aabr.center()));
                         updater.insert(emitter, Lifetime::new(ENEMY_DEAD_EMITTER_LIFETIME));
                     }
@@ -440,6 +793,8 @@ impl<'a> System<'a> for EnemyEmitterSystem {
         Entities<'a>,
         ReadExpect<'a, Sprites>,
         Option<Read<'a, Ships>>,
+        Read<'a, Scripts>,
+        Read<'a, Palette>,
         Write<'a, Phase>,
         Write<'a, EnemiesLeft>,
         WriteStorage<'a, EnemyEmitter>,
@@ -449,7 +804,18 @@ impl<'a> System<'a> for EnemyEmitterSystem {
 
     fn run(
         &mut self,
-        (entities, sprites, ships, mut phase, mut enemies_left, mut emitter, pos, updater): Self::SystemData,
+        (
+            entities,
+            sprites,
+            ships,
+            scripts,
+            palette,
+            mut phase,
+            mut enemies_left,
+            mut emitter,
+            pos,
+            updater,
+        ): Self::SystemData,
     ) {
         if let Some(ships) = ships {
             enemies_left.0 = 0;
@@ -467,10 +833,10 @@ impl<'a> System<'a> for EnemyEmitterSystem {
                 }
                 emitter.current_time += 1.0;
 
-                if let Some((time, type_)) = emitter.spawner.first() {
+                if let Some((time, wave)) = emitter.spawner.first() {
                     if *time < emitter.current_time {
-                        EnemyEmitter::spawn_enemy_with_resource_usage(
-                            &entities, &updater, &sprites, &ships, *type_, &pos,
+                        EnemyEmitter::spawn_wave(
+                            &entities, &updater, &sprites, &ships, &scripts, &palette, *wave, &pos,
                         );
 
                         emitter.spawner.remove(0);
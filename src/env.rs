@@ -0,0 +1,366 @@
+use crate::{
+    effect::Camera, enemy::Enemy, input::Input, lives::Lives, mode::GameMode, money::Wallet,
+    phase::Phase, physics::*, pickup::Pickup, player::Player, projectile::Projectile,
+    upgrade::Upgrades, Game, HEIGHT, WIDTH,
+};
+use anyhow::{bail, Result};
+use specs_blit::{
+    specs::{Join, WorldExt},
+    PixelBuffer,
+};
+use std::io::{BufRead, Write};
+
+/// Reward for every piece of scrap collected.
+const SCRAP_REWARD: f64 = 0.01;
+/// Penalty for every life lost.
+const LIFE_PENALTY: f64 = 1.0;
+/// Width and height of the blocks of pixels averaged into a single pixel of the observation.
+const PIXEL_SCALE: usize = 4;
+/// Maximum amount of objects in a structured observation, the furthest ones are left out.
+const MAX_OBJECTS: usize = 16;
+
+/// How the agent sees the game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ObservationKind {
+    /// A downscaled grayscale copy of the screen.
+    Pixels,
+    /// The paddle and the closest projectiles, enemies and pickups.
+    Objects,
+}
+
+/// What kind of object is observed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ObjectKind {
+    Paddle,
+    Projectile,
+    Enemy,
+    Pickup,
+}
+
+/// A single object in a structured observation.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub kind: ObjectKind,
+    /// The center of the object.
+    pub pos: (f64, f64),
+    pub vel: (f64, f64),
+}
+
+/// What the agent sees after a step.
+#[derive(Debug, Clone)]
+pub enum Observation {
+    /// Brightness between 0 and 1 of every pixel, row by row.
+    Pixels {
+        width: usize,
+        height: usize,
+        pixels: Vec<f32>,
+    },
+    /// The paddle first, followed by the other objects sorted by distance from the paddle.
+    Objects(Vec<Object>),
+}
+
+/// What the agent wants to do.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Action {
+    /// Vertical position of the mouse.
+    pub y: i32,
+    /// Whether the mouse button is held.
+    pub hold: bool,
+}
+
+/// The result of a single step.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f64,
+    /// Whether the game is over and the environment needs to be reset.
+    pub done: bool,
+}
+
+/// A headless single player game that can be controlled step by step.
+pub struct Environment {
+    game: Game<'static, 'static>,
+    kind: ObservationKind,
+    /// Amount of game ticks simulated with the same action every step.
+    frame_skip: usize,
+    ticks: usize,
+}
+
+impl Environment {
+    pub fn new(kind: ObservationKind) -> Result<Self> {
+        Ok(Self {
+            game: Game::headless()?,
+            kind,
+            frame_skip: 1,
+            ticks: 0,
+        })
+    }
+
+    pub fn with_frame_skip(mut self, frame_skip: usize) -> Self {
+        self.frame_skip = frame_skip.max(1);
+
+        self
+    }
+
+    /// Start a new game, the same seed and actions always result in the same game.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        quad_rand::srand(seed);
+
+        *self.game.world.write_resource::<Input>() = Input::default();
+        self.game.start(GameMode::Single);
+        self.ticks = 0;
+
+        // Nothing is rendered yet, show the empty screen
+        self.game.background.copy(
+            self.game.world.write_resource::<PixelBuffer>().pixels_mut(),
+            (0, 0),
+        );
+
+        self.observe()
+    }
+
+    /// Simulate the game with the action.
+    pub fn step(&mut self, action: Action) -> Step {
+        let mut reward = 0.0;
+        for _ in 0..self.frame_skip {
+            reward += self.tick(action);
+
+            if self.done() {
+                break;
+            }
+        }
+
+        Step {
+            observation: self.observe(),
+            reward,
+            done: self.done(),
+        }
+    }
+
+    /// Simulate a single tick and return the reward.
+    fn tick(&mut self, action: Action) -> f64 {
+        let world = &mut self.game.world;
+
+        // The shop isn't part of the environment, buy what's useful and continue
+        if *world.read_resource::<Phase>() == Phase::Setup {
+            let mut phase = world.write_resource::<Phase>();
+            world
+                .write_resource::<Upgrades>()
+                .autopilot(&mut world.write_resource::<Wallet>(), &mut phase);
+        }
+
+        {
+            let mut input = world.write_resource::<Input>();
+            input.handle_mouse_move(0, action.y);
+            input.handle_mouse_button(action.hold);
+        }
+
+        // Clear the screen like drawing does every frame, so the observation only shows the last
+        // tick instead of all skipped frames on top of each other
+        let offset = world.read_resource::<Camera>().offset();
+        self.game
+            .background
+            .copy(world.write_resource::<PixelBuffer>().pixels_mut(), offset);
+
+        let (scrap, lives) = self.stats();
+        self.game.tick();
+        self.ticks += 1;
+        let (new_scrap, new_lives) = self.stats();
+
+        // The lives are restored every level so only count the ones lost
+        new_scrap.saturating_sub(scrap) as f64 * SCRAP_REWARD
+            - lives.saturating_sub(new_lives) as f64 * LIFE_PENALTY
+    }
+
+    /// The current scrap and lives.
+    fn stats(&self) -> (usize, u8) {
+        let world = &self.game.world;
+
+        (
+            world.read_resource::<Wallet>().money(),
+            world.try_fetch::<Lives>().map_or(0, |lives| lives.amount()),
+        )
+    }
+
+    fn done(&self) -> bool {
+        *self.game.world.read_resource::<Phase>() == Phase::GameOver
+            || self.ticks >= crate::HEADLESS_MAX_TICKS
+    }
+
+    fn observe(&self) -> Observation {
+        match self.kind {
+            ObservationKind::Pixels => self.observe_pixels(),
+            ObservationKind::Objects => self.observe_objects(),
+        }
+    }
+
+    fn observe_pixels(&self) -> Observation {
+        let (width, height) = (WIDTH / PIXEL_SCALE, HEIGHT / PIXEL_SCALE);
+
+        let buffer = self.game.world.read_resource::<PixelBuffer>();
+        let pixels = (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width * PIXEL_SCALE, index / width * PIXEL_SCALE);
+
+                let sum = (0..PIXEL_SCALE * PIXEL_SCALE)
+                    .map(|offset| {
+                        let pixel = buffer.pixels()
+                            [(y + offset / PIXEL_SCALE) * WIDTH + x + offset % PIXEL_SCALE];
+
+                        ((pixel & 0xFF) + ((pixel >> 8) & 0xFF) + ((pixel >> 16) & 0xFF)) as f32
+                    })
+                    .sum::<f32>();
+
+                sum / (PIXEL_SCALE * PIXEL_SCALE * 3 * 0xFF) as f32
+            })
+            .collect();
+
+        Observation::Pixels {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn observe_objects(&self) -> Observation {
+        let world = &self.game.world;
+        let (pos, vel, bb) = (
+            world.read_storage::<Position>(),
+            world.read_storage::<Velocity>(),
+            world.read_storage::<BoundingBox>(),
+        );
+        let (player, projectile, enemy, pickup) = (
+            world.read_storage::<Player>(),
+            world.read_storage::<Projectile>(),
+            world.read_storage::<Enemy>(),
+            world.read_storage::<Pickup>(),
+        );
+
+        let mut objects = (
+            &pos,
+            &vel,
+            &bb,
+            (&player).maybe(),
+            (&projectile).maybe(),
+            (&enemy).maybe(),
+            (&pickup).maybe(),
+        )
+            .join()
+            .filter_map(|(pos, vel, bb, player, projectile, enemy, pickup)| {
+                let kind = match (player, projectile, enemy, pickup) {
+                    (Some(_), _, _, _) => ObjectKind::Paddle,
+                    (_, Some(_), _, _) => ObjectKind::Projectile,
+                    (_, _, Some(_), _) => ObjectKind::Enemy,
+                    (_, _, _, Some(_)) => ObjectKind::Pickup,
+                    _ => return None,
+                };
+                let center = pos.0 + bb.center_offset();
+
+                Some(Object {
+                    kind,
+                    pos: (center.x, center.y),
+                    vel: (vel.x, vel.y),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Put the paddle first and the rest by distance from it
+        let paddle = objects
+            .iter()
+            .find(|object| object.kind == ObjectKind::Paddle)
+            .map_or((0.0, 0.0), |object| object.pos);
+        let distance = |object: &Object| {
+            if object.kind == ObjectKind::Paddle {
+                -1.0
+            } else {
+                (object.pos.0 - paddle.0).hypot(object.pos.1 - paddle.1)
+            }
+        };
+        objects.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        objects.truncate(MAX_OBJECTS + 1);
+
+        Observation::Objects(objects)
+    }
+}
+
+impl ObservationKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pixels" => Some(ObservationKind::Pixels),
+            "objects" => Some(ObservationKind::Objects),
+            _ => None,
+        }
+    }
+}
+
+impl Observation {
+    /// All values as a single line of numbers separated by spaces, pixels are preceded by the
+    /// width and height and objects are written as `kind x y velocity_x velocity_y`.
+    fn to_line(&self) -> String {
+        match self {
+            Observation::Pixels {
+                width,
+                height,
+                pixels,
+            } => format!(
+                "{} {} {}",
+                width,
+                height,
+                pixels
+                    .iter()
+                    .map(|pixel| format!("{:.3}", pixel))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            Observation::Objects(objects) => objects
+                .iter()
+                .map(|object| {
+                    format!(
+                        "{} {:.2} {:.2} {:.2} {:.2}",
+                        object.kind as u8, object.pos.0, object.pos.1, object.vel.0, object.vel.1
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Control an environment with text commands so agents can be trained from other languages.
+///
+/// Every command is answered with a line containing the reward, whether the game is done and the
+/// observation:
+///
+/// - `reset <seed>`
+/// - `step <mouse y> <hold 0 or 1>`
+pub fn serve(mut env: Environment, input: impl BufRead, mut output: impl Write) -> Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        let mut args = line.split_whitespace();
+
+        let step = match (args.next(), args.next(), args.next()) {
+            (Some("reset"), seed, None) => Step {
+                observation: env.reset(seed.and_then(|seed| seed.parse().ok()).unwrap_or(0)),
+                reward: 0.0,
+                done: false,
+            },
+            (Some("step"), Some(y), hold) => env.step(Action {
+                y: y.parse()?,
+                hold: hold == Some("1"),
+            }),
+            (None, _, _) => continue,
+            _ => bail!("Unknown command \"{}\"", line),
+        };
+
+        writeln!(
+            output,
+            "{} {} {}",
+            step.reward,
+            step.done as u8,
+            step.observation.to_line()
+        )?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
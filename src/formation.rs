@@ -0,0 +1,107 @@
+use crate::{physics::*, random};
+use specs_blit::specs::*;
+
+type Vec2 = vek::Vec2<f64>;
+
+/// Distance between the ships in a formation.
+const SPACING: f64 = 24.0;
+
+/// The shape a group of enemies flies in, the first one is the leader.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Formation {
+    /// Next to each other vertically.
+    Line,
+    /// Behind each other horizontally.
+    Column,
+    /// Two lines fanning out behind the leader.
+    V,
+    /// A circle around the leader.
+    Ring,
+}
+
+impl Formation {
+    pub fn random() -> Self {
+        *random::index(&[
+            Formation::Line,
+            Formation::Column,
+            Formation::V,
+            Formation::Ring,
+        ])
+    }
+
+    /// The positions relative to the leader of all the followers.
+    pub fn offsets(self, followers: usize) -> Vec<Vec2> {
+        (0..followers)
+            .map(|index| {
+                // Alternate between above and below the leader
+                let row = (index / 2 + 1) as f64;
+                let side = if index % 2 == 0 { -1.0 } else { 1.0 };
+
+                match self {
+                    Formation::Line => Vec2::new(0.0, side * row * SPACING),
+                    Formation::Column => Vec2::new((index + 1) as f64 * SPACING, 0.0),
+                    Formation::V => Vec2::new(row * SPACING, side * row * SPACING),
+                    Formation::Ring => {
+                        let angle = index as f64 / followers as f64 * std::f64::consts::TAU;
+
+                        Vec2::new(angle.cos(), angle.sin()) * SPACING * 1.5
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Keeps an enemy at an offset from the leader of its formation.
+#[derive(Component, Debug)]
+pub struct Follower {
+    leader: Entity,
+    offset: Vec2,
+}
+
+impl Follower {
+    pub fn new(leader: Entity, offset: Vec2) -> Self {
+        Self { leader, offset }
+    }
+}
+
+/// System that moves the formations with their leaders.
+pub struct FormationSystem;
+impl<'a> System<'a> for FormationSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Follower>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, follower, pos, mut vel, updater): Self::SystemData) {
+        let leaders = (&*entities, &follower, &pos)
+            .join()
+            .map(|(entity, follower, follower_pos)| {
+                let leader = match (pos.get(follower.leader), vel.get(follower.leader)) {
+                    (Some(leader_pos), Some(leader_vel)) if entities.is_alive(follower.leader) => {
+                        Some((leader_pos.0, leader_vel.0))
+                    }
+                    _ => None,
+                };
+
+                (entity, follower.offset, follower_pos.0, leader)
+            })
+            .collect::<Vec<_>>();
+
+        for (entity, offset, pos, leader) in leaders {
+            match leader {
+                Some((leader_pos, leader_vel)) => {
+                    if let Some(vel) = vel.get_mut(entity) {
+                        // Fly along with the leader and correct the distance from it
+                        vel.0 = leader_vel + (leader_pos + offset - pos);
+                    }
+                }
+                // Break formation and continue with their own movement
+                None => updater.remove::<Follower>(entity),
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{input::Input, upgrade::Upgrades};
+use crate::{color::Palette, input::Input, upgrade::Upgrades};
 use direct_gui::{
     controls::{Button, ControlState},
     Gui as InternalGui,
@@ -51,3 +51,27 @@ impl Gui {
         self.internal.draw_to_buffer(buffer.pixels_mut());
     }
 }
+
+/// Draw a horizontal meter that's filled from the left for a fraction of its width.
+pub fn draw_meter(
+    buffer: &mut PixelBuffer,
+    palette: &Palette,
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+    fraction: f64,
+    color: u32,
+) {
+    let filled = (fraction.clamp(0.0, 1.0) * width as f64) as usize;
+
+    let buffer_width = buffer.width();
+    let pixels = buffer.pixels_mut();
+    for meter_y in y..(y + height).min(crate::HEIGHT) {
+        for meter_x in x..(x + width).min(buffer_width) {
+            pixels[meter_y * buffer_width + meter_x] = if meter_x - x < filled {
+                color
+            } else {
+                palette.ui_background
+            };
+        }
+    }
+}
@@ -1,8 +1,14 @@
-#[derive(Debug, Default)]
+use derive_deref::{Deref, DerefMut};
+use miniquad::KeyCode;
+use std::collections::HashSet;
+
+#[derive(Debug, Default, Clone)]
 pub struct Input {
     mouse_x: i32,
     mouse_y: i32,
     mouse_down: bool,
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
 }
 
 impl Input {
@@ -21,6 +27,21 @@ impl Input {
         self.mouse_down
     }
 
+    /// Get whether a keyboard key is pressed.
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Get whether a keyboard key went down since the last frame.
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Forget the keys that were pressed this frame, should be called after every update.
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+    }
+
     /// Handle miniquad mouse button events.
     pub fn handle_mouse_button(&mut self, is_down: bool) {
         self.mouse_down = is_down;
@@ -31,4 +52,19 @@ impl Input {
         self.mouse_x = x;
         self.mouse_y = y;
     }
+
+    /// Handle miniquad key events.
+    pub fn handle_key(&mut self, key: KeyCode, is_down: bool) {
+        if is_down {
+            if self.keys_down.insert(key) {
+                self.keys_pressed.insert(key);
+            }
+        } else {
+            self.keys_down.remove(&key);
+        }
+    }
 }
+
+/// The input of the other player in an online game.
+#[derive(Debug, Default, Deref, DerefMut)]
+pub struct RemoteInput(pub Input);
@@ -0,0 +1,1050 @@
+mod ability;
+mod background;
+mod bot;
+mod color;
+mod editor;
+mod effect;
+mod enemy;
+mod entity;
+mod env;
+mod formation;
+mod gui;
+mod input;
+mod lives;
+mod mask;
+mod mode;
+mod money;
+mod movement;
+mod net;
+mod particle;
+mod pattern;
+mod phase;
+mod physics;
+mod pickup;
+mod planet;
+mod player;
+mod postprocess;
+mod projectile;
+mod random;
+mod render;
+mod score;
+mod script;
+mod ship;
+mod spectate;
+mod sprite;
+pub mod spritesheet;
+mod upgrade;
+mod versus;
+
+use crate::{
+    background::Background,
+    color::Palette,
+    enemy::EnemiesLeft,
+    gui::Gui,
+    input::{Input, RemoteInput},
+    lives::Lives,
+    mode::GameMode,
+    money::Wallet,
+    net::{Lobby, Session},
+    phase::Phase,
+    physics::Position,
+    postprocess::PostProcess,
+    render::Render,
+    score::Scores,
+    script::Scripts,
+    spectate::{Snapshot, Spectator, SpectatorServer, SPECTATOR_PORT},
+    sprite::Sprites,
+    upgrade::Upgrades,
+    versus::Commander,
+};
+use anyhow::Result;
+use miniquad::{conf::Conf, Context, EventHandler, KeyCode, KeyMods, MouseButton};
+use specs_blit::{specs::prelude::*, PixelBuffer, Sprite};
+
+pub const WIDTH: usize = 400;
+pub const HEIGHT: usize = 300;
+
+/// Frames without input on the menu before the computer starts playing.
+const ATTRACT_DELAY: usize = 10 * 60;
+/// Frames a headless game can last before it's stopped.
+const HEADLESS_MAX_TICKS: usize = 60 * 60 * 60;
+/// Frames the particle benchmark measures.
+const PARTICLE_BENCHMARK_FRAMES: usize = 600;
+
+/// Our game state.
+struct Game<'a, 'b> {
+    /// The specs world.
+    world: World,
+    /// The specs dispatcher, it needs these lifetimes.
+    dispatcher: Dispatcher<'a, 'b>,
+    /// Our wrapper around the OpenGL calls, not available when running headless.
+    render: Option<Render>,
+
+    level: usize,
+    background: Background,
+    /// Effects applied to the pixels before they are shown.
+    post_process: PostProcess,
+
+    /// Setting up an online game on the menu.
+    lobby: Lobby,
+    /// The connection to the other player when playing online.
+    session: Option<Session>,
+    /// Sends every tick to the people watching.
+    spectator_server: Option<SpectatorServer>,
+    /// Watching a game on another machine instead of playing.
+    spectator: Option<Spectator>,
+    /// Frames since the last input on the menu.
+    idle: usize,
+}
+
+impl<'a, 'b> Game<'a, 'b> {
+    /// Setup the ECS and load the systems.
+    pub fn new(ctx: &mut Context) -> Result<Self> {
+        // Setup the OpenGL render part
+        Game::with_render(Some(Render::new(ctx, WIDTH, HEIGHT)))
+    }
+
+    /// Setup the game without a window, only the simulation can be used.
+    pub fn headless() -> Result<Self> {
+        Game::with_render(None)
+    }
+
+    fn with_render(render: Option<Render>) -> Result<Self> {
+        let mut world = Game::create_world();
+
+        // Setup the dispatcher with the blit system
+        let dispatcher = DispatcherBuilder::new()
+            .with(versus::CommanderSystem, "commander", &[])
+            .with(script::ScriptSystem, "script", &[])
+            .with(
+                projectile::ProjectileEmitterSystem,
+                "projectile_emitter",
+                &["commander", "script"],
+            )
+            .with(pattern::MineSystem, "mine", &[])
+            .with(player::GunSystem, "gun", &["projectile_emitter"])
+            .with(player::ChargeSystem, "charge", &[])
+            .with(projectile::MissileSystem, "missile", &[])
+            .with(projectile::LaserSystem, "laser", &[])
+            .with(particle::ParticleEmitterSystem, "particle_emitter", &[])
+            .with(particle::ParticleSystem, "particle", &["particle_emitter"])
+            .with(entity::LifetimeSystem, "lifetime", &[])
+            .with(player::PlayerSystem, "player", &[])
+            .with(bot::AutopilotSystem, "autopilot", &[])
+            .with(ability::AbilitySystem, "ability", &["player"])
+            .with(projectile::ProjectileSystem, "projectile", &["player"])
+            .with(enemy::EnemySystem, "enemy", &[])
+            .with(enemy::EnemyEmitterSystem, "enemy_emitter", &[])
+            .with(
+                movement::MovementSystem,
+                "movement",
+                &["projectile_emitter"],
+            )
+            .with(
+                formation::FormationSystem,
+                "formation",
+                &["movement", "script"],
+            )
+            .with(
+                physics::VelocitySystem,
+                "velocity",
+                &[
+                    "player",
+                    "autopilot",
+                    "ability",
+                    "movement",
+                    "script",
+                    "formation",
+                ],
+            )
+            .with(physics::DragSystem, "drag", &["velocity"])
+            .with(physics::BoundingBoxSystem, "bb", &["velocity"])
+            .with(enemy::EnemyCollisionSystem, "enemy_collision", &["bb"])
+            .with(pickup::PickupSystem, "pickup", &["projectile", "velocity"])
+            .with(pickup::PickupEmitterSystem, "pickup_emitter", &[])
+            .with(
+                planet::PlanetSystem,
+                "planet",
+                &["projectile", "enemy", "pickup"],
+            )
+            .with(
+                effect::CameraSystem,
+                "camera",
+                &["projectile", "enemy", "enemy_collision"],
+            )
+            .with(sprite::AnimationSystem, "animation", &[])
+            .with(
+                sprite::SpritePositionSystem,
+                "sprite_pos",
+                &["velocity", "camera", "animation"],
+            )
+            .with(
+                sprite::SpriteRotationSystem,
+                "sprite_rot",
+                &["velocity", "animation"],
+            )
+            .with_thread_local(particle::ParticleRenderSystem)
+            .with_thread_local(specs_blit::RenderSystem)
+            .with_thread_local(planet::PlanetRenderSystem)
+            .with_thread_local(effect::ScreenFlashSystem)
+            .build();
+
+        // Load some sprites
+        world.insert(Sprites::generate().expect("Could not generate sprites"));
+
+        let mut game = Self {
+            world,
+            dispatcher,
+            render,
+            level: 0,
+            background: Background::new(quad_rand::rand() as u64),
+            post_process: PostProcess::new(WIDTH, HEIGHT),
+            lobby: Lobby::default(),
+            session: None,
+            spectator_server: None,
+            spectator: None,
+            idle: 0,
+        };
+        game.switch_phase(Phase::default());
+
+        Ok(game)
+    }
+
+    /// Setup the ECS with all components and resources except the sprites.
+    fn create_world() -> World {
+        // Setup the ECS system
+        let mut world = World::new();
+
+        // Load the game components
+        world.register::<physics::Position>();
+        world.register::<physics::Velocity>();
+        world.register::<physics::Speed>();
+        world.register::<physics::Drag>();
+        world.register::<physics::BoundingBox>();
+
+        world.register::<player::Player>();
+        world.register::<player::Gun>();
+        world.register::<player::Charge>();
+        world.register::<ability::Dash>();
+        world.register::<ability::Barrier>();
+
+        world.register::<enemy::Enemy>();
+        world.register::<enemy::EnemyEmitter>();
+
+        world.register::<pickup::Pickup>();
+        world.register::<pickup::PickupEmitter>();
+
+        world.register::<money::Money>();
+
+        world.register::<movement::Zigzag>();
+        world.register::<movement::Homing>();
+        world.register::<movement::Circling>();
+        world.register::<movement::StopAndGo>();
+        world.register::<movement::Dive>();
+        world.register::<movement::Path>();
+        world.register::<movement::Retreat>();
+
+        world.register::<formation::Follower>();
+
+        world.register::<script::Script>();
+
+        world.register::<particle::ParticleEmitter>();
+
+        world.register::<projectile::Projectile>();
+        world.register::<projectile::ProjectileEmitter>();
+        world.register::<projectile::SplitInto>();
+        world.register::<projectile::ReflectedBy>();
+        world.register::<projectile::Damage>();
+        world.register::<projectile::Missile>();
+        world.register::<projectile::Laser>();
+        world.register::<pattern::Mine>();
+
+        world.register::<entity::Lifetime>();
+
+        world.register::<upgrade::HoldProjectile>();
+
+        world.register::<effect::ScreenFlash>();
+        world.register::<effect::ShakeOnDeath>();
+
+        world.register::<planet::Planet>();
+
+        world.register::<sprite::RotationFollowsVelocity>();
+        world.register::<sprite::Animation>();
+        world.register::<sprite::SpriteId>();
+
+        // Load the sprite rendering component
+        world.register::<Sprite>();
+
+        // Add the pixel buffer as a resource so it can be accessed from the RenderSystem later, to be
+        // updated every frame
+        world.insert(PixelBuffer::new(WIDTH, HEIGHT));
+
+        // The particles are drawn directly into the pixel buffer
+        world.insert(particle::Particles::default());
+
+        // The screen shake
+        world.insert(effect::Camera::default());
+
+        // The colors, can be changed for colorblind players
+        world.insert(Palette::default());
+
+        // Add the input system
+        world.insert(Input::default());
+        world.insert(RemoteInput::default());
+
+        // Add the gui system
+        world.insert(Gui::new(WIDTH, HEIGHT));
+
+        // The current phase
+        world.insert(Phase::default());
+
+        // Enemies left
+        world.insert(EnemiesLeft::default());
+
+        // Money
+        world.insert(Wallet::default());
+
+        // The scrap earned by each player
+        world.insert(Scores::default());
+
+        // Single player or co-op
+        world.insert(GameMode::default());
+
+        // The upgrades
+        world.insert(Upgrades::default());
+
+        // The enemy behaviours loaded from disk
+        world.insert(Scripts::default());
+
+        world
+    }
+
+    pub fn switch_phase(&mut self, phase: Phase) {
+        {
+            let mut old_phase = self.world.write_resource::<Phase>();
+            *old_phase = phase.clone();
+        }
+
+        // Clear all entities
+        self.world.delete_all();
+        self.world.write_resource::<particle::Particles>().clear();
+
+        match phase {
+            Phase::Menu => {}
+            Phase::Editor => self.world.insert(editor::Editor::new()),
+            Phase::Initialize => {
+                self.level = 1;
+                self.world.write_resource::<Wallet>().reset();
+                self.world.write_resource::<Upgrades>().reset();
+
+                let players = self.world.read_resource::<GameMode>().players();
+                self.world.write_resource::<Scores>().reset(players);
+
+                // Generate new ships every game, online games are already seeded here so both
+                // players get the same ones
+                self.world.insert(ship::Ships::generate());
+
+                self.switch_phase(Phase::Play);
+            }
+            Phase::Setup => {
+                self.level += 1;
+            }
+            Phase::Play => {
+                let flash = self.world.read_resource::<Palette>().ui;
+                self.world
+                    .create_entity()
+                    .with(effect::ScreenFlash::new(flash))
+                    .with(entity::Lifetime::new(5.0))
+                    .build();
+
+                // Render background planet
+                let sprite = self.world.read_resource::<Sprites>().planet.clone();
+                self.world
+                    .create_entity()
+                    .with(sprite.sprite())
+                    .with(sprite.id())
+                    .with(Position::new(0.0, 0.0))
+                    .with(planet::Planet::default())
+                    .build();
+
+                self.world.insert(Lives::new(3));
+
+                let mode = *self.world.read_resource::<GameMode>();
+                let enemy_emitter = if mode == GameMode::Versus {
+                    // The enemies are deployed by the second player
+                    self.world.insert(Commander::new());
+
+                    enemy::EnemyEmitter::timer(versus::level_time(self.level))
+                } else {
+                    self.world.remove::<Commander>();
+
+                    enemy::EnemyEmitter::new(Some(self.level))
+                };
+
+                self.world
+                    .create_entity()
+                    .with(enemy_emitter)
+                    .with(pickup::PickupEmitter::new())
+                    .build();
+
+                // Spawn the paddles
+                for (id, controller) in mode.controllers().iter().enumerate() {
+                    player::spawn_player(&mut self.world, id as u8, *controller)
+                        .expect("Couldn't spawn player");
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Start a new game from the menu or the game over screen.
+    pub fn start(&mut self, mode: GameMode) {
+        self.world.insert(mode);
+        self.switch_phase(Phase::Initialize);
+    }
+
+    /// Start a game with another instance, both need to use the same seed.
+    pub fn start_online(&mut self, session: Session, seed: u64, host: bool) {
+        quad_rand::srand(seed);
+
+        // Start with a fresh world so the entity IDs are the same on both instances
+        let mut world = Game::create_world();
+        world.insert(self.world.remove::<Sprites>().expect("Sprites are missing"));
+        world.insert(self.world.remove::<Input>().unwrap_or_default());
+        world.insert(self.world.remove::<Palette>().unwrap_or_default());
+        // Changing the scripts while playing would only change them for one player
+        world.write_resource::<Scripts>().set_hot_reload(false);
+        self.world = world;
+
+        self.session = Some(session);
+        self.start(GameMode::Online { host });
+    }
+
+    /// Whether the computer is playing to show off the game on the menu.
+    pub fn attracting(&self) -> bool {
+        *self.world.read_resource::<GameMode>() == GameMode::Autopilot
+            && *self.world.read_resource::<Phase>() != Phase::Menu
+    }
+
+    /// Disconnect and go back to the menu.
+    pub fn stop_online(&mut self, reason: Option<String>) {
+        self.session = None;
+        self.lobby = match reason {
+            Some(reason) => Lobby::Failed(reason),
+            None => Lobby::Idle,
+        };
+        *self.world.write_resource::<RemoteInput>() = RemoteInput::default();
+        self.world.write_resource::<Scripts>().set_hot_reload(true);
+
+        self.switch_phase(Phase::Menu);
+    }
+
+    /// Simulate a single step of the game.
+    pub fn tick(&mut self) {
+        // Update specs
+        self.dispatcher.dispatch(&self.world);
+
+        // Add/remove entities added in dispatch through `LazyUpdate`
+        self.world.maintain();
+
+        if *self.world.read_resource::<Phase>() == Phase::Editor {
+            self.world.write_resource::<editor::Editor>().update(
+                &mut self.world.write_resource::<Phase>(),
+                &self.world.read_resource::<Input>(),
+            );
+        }
+
+        self.world.write_resource::<Input>().end_frame();
+
+        if let Some(server) = self.spectator_server.as_mut() {
+            server.broadcast(&Snapshot::capture(
+                &self.world,
+                self.level,
+                &self.background,
+            ));
+        }
+
+        if *self.world.read_resource::<Phase>() == Phase::Setup {
+            let mut upgrades = self.world.write_resource::<Upgrades>();
+            let mut wallet = self.world.write_resource::<Wallet>();
+            let mut phase = self.world.write_resource::<Phase>();
+
+            if *self.world.read_resource::<GameMode>() == GameMode::Autopilot {
+                upgrades.autopilot(&mut wallet, &mut phase);
+            }
+
+            // Both players can buy upgrades when playing online
+            upgrades.update(
+                &mut wallet,
+                &mut phase,
+                &self.world.read_resource::<Input>(),
+            );
+            upgrades.update(
+                &mut wallet,
+                &mut phase,
+                &self.world.read_resource::<RemoteInput>(),
+            );
+        }
+
+        let mut phase = (*self.world.read_resource::<Phase>()).clone();
+        if (phase == Phase::Play || phase == Phase::WaitingForLastEnemy)
+            && self.world.read_resource::<Lives>().is_dead()
+        {
+            phase = Phase::SwitchTo(Box::new(Phase::GameOver));
+        }
+
+        if let Phase::SwitchTo(new_phase) = phase {
+            self.switch_phase(*new_phase);
+        }
+    }
+
+    /// Simulate a step when the inputs of both players have arrived.
+    pub fn tick_online(&mut self, mut session: Session) {
+        let live = (*self.world.read_resource::<Input>()).clone();
+
+        match session.advance(&live) {
+            Ok(Some((local, remote))) => {
+                // Simulate with the delayed inputs the other player also has
+                *self.world.write_resource::<Input>() = local;
+                *self.world.write_resource::<RemoteInput>() = RemoteInput(remote);
+
+                self.tick();
+
+                session.verify(net::checksum(&self.world));
+
+                let mut input = self.world.write_resource::<Input>();
+                *input = live;
+                input.end_frame();
+            }
+            // Still waiting for the other player
+            Ok(None) => (),
+            Err(err) => return self.stop_online(Some(format!("Connection lost: {}", err))),
+        }
+
+        self.session = Some(session);
+    }
+
+    pub fn render_phase(&mut self) {
+        let phase = self.world.read_resource::<Phase>();
+
+        let mut buffer = self.world.write_resource::<PixelBuffer>();
+        let mut gui = self.world.write_resource::<Gui>();
+        match *phase {
+            Phase::Menu => {
+                // Render the GUI
+                gui.draw_label(&mut buffer, "Click to play!", 130, 145);
+                gui.draw_label(&mut buffer, "Press 2 for local co-op", 100, 165);
+                gui.draw_label(&mut buffer, "Press 3 for versus", 115, 180);
+                gui.draw_label(&mut buffer, "Press E to edit the sprites", 80, 195);
+                gui.draw_label(&mut buffer, self.lobby.status(), 20, 230);
+                gui.draw_label(&mut buffer, "Press F1 to F5 for screen effects", 20, 250);
+                gui.draw_label(
+                    &mut buffer,
+                    format!(
+                        "Press F6 for colors: {}",
+                        self.world.read_resource::<Palette>().preset().name()
+                    ),
+                    20,
+                    285,
+                );
+                gui.draw_label(
+                    &mut buffer,
+                    match self.spectator_server.as_ref() {
+                        Some(server) => format!(
+                            "Broadcasting on port {}, {} watching",
+                            SPECTATOR_PORT,
+                            server.spectators()
+                        ),
+                        None => "Press B to let others watch".to_string(),
+                    },
+                    20,
+                    270,
+                );
+            }
+            Phase::Setup => {
+                let input = self.world.read_resource::<Input>();
+                gui.draw(&mut buffer, &input);
+
+                let wallet = self.world.read_resource::<Wallet>();
+                let upgrades = self.world.read_resource::<Upgrades>();
+                upgrades.render(&mut buffer, &mut gui, &wallet, self.level);
+            }
+            Phase::Play | Phase::WaitingForLastEnemy => {
+                let palette = self.world.read_resource::<Palette>();
+                let lives = self.world.read_resource::<Lives>();
+                lives.render(&mut buffer, 20, 5);
+
+                for (player, gun) in (
+                    &self.world.read_storage::<player::Player>(),
+                    &self.world.read_storage::<player::Gun>(),
+                )
+                    .join()
+                {
+                    gun.render(&mut buffer, &palette, 20 + player.id() as usize * 50, 19);
+                }
+                for (player, dash) in (
+                    &self.world.read_storage::<player::Player>(),
+                    &self.world.read_storage::<ability::Dash>(),
+                )
+                    .join()
+                {
+                    dash.render(&mut buffer, &palette, 20 + player.id() as usize * 50, 24);
+                }
+                for (player, barrier) in (
+                    &self.world.read_storage::<player::Player>(),
+                    &self.world.read_storage::<ability::Barrier>(),
+                )
+                    .join()
+                {
+                    barrier.render(&mut buffer, &palette, 20 + player.id() as usize * 50, 28);
+                }
+
+                for (pos, bb, charge) in (
+                    &self.world.read_storage::<Position>(),
+                    &self.world.read_storage::<physics::BoundingBox>(),
+                    &self.world.read_storage::<player::Charge>(),
+                )
+                    .join()
+                {
+                    charge.render(&mut buffer, &palette, pos, bb);
+                }
+
+                gui.draw_label(&mut buffer, format!("Level {}", self.level), 70, 5);
+
+                match self.world.try_fetch::<Commander>() {
+                    Some(commander) => {
+                        gui.draw_label(
+                            &mut buffer,
+                            format!("Energy {}", commander.energy()),
+                            150,
+                            5,
+                        );
+                        commander.render(&mut buffer, &palette);
+                    }
+                    None => gui.draw_label(
+                        &mut buffer,
+                        format!("Enemies {}", self.world.read_resource::<EnemiesLeft>().0),
+                        150,
+                        5,
+                    ),
+                }
+
+                gui.draw_label(
+                    &mut buffer,
+                    format!("Scrap {}", self.world.read_resource::<Wallet>().money()),
+                    250,
+                    5,
+                );
+
+                if self.world.read_resource::<GameMode>().players() > 1 {
+                    gui.draw_label(
+                        &mut buffer,
+                        self.world.read_resource::<Scores>().summary(),
+                        250,
+                        17,
+                    );
+                }
+
+                if *self.world.read_resource::<GameMode>() == GameMode::Autopilot {
+                    gui.draw_label(&mut buffer, "Click to play!", 130, 145);
+                }
+
+                if let Some(tick) = self.session.as_ref().and_then(Session::desync) {
+                    gui.draw_label(&mut buffer, format!("DESYNC at tick {}", tick), 20, 285);
+                }
+            }
+            Phase::Editor => {
+                let palette = self.world.read_resource::<Palette>();
+                self.world.read_resource::<editor::Editor>().render(
+                    &mut buffer,
+                    &mut gui,
+                    &palette,
+                );
+            }
+            Phase::GameOver => {
+                if *self.world.read_resource::<GameMode>() == GameMode::Versus {
+                    gui.draw_label(&mut buffer, "The aliens won!", 135, 110);
+                }
+                gui.draw_label(&mut buffer, "GAME OVER!", 150, 130);
+
+                gui.draw_label(
+                    &mut buffer,
+                    format!(
+                        "Level {}\nScrap {}",
+                        self.level,
+                        self.world.read_resource::<Wallet>().money()
+                    ),
+                    165,
+                    150,
+                );
+                if self.world.read_resource::<GameMode>().players() > 1 {
+                    gui.draw_label(
+                        &mut buffer,
+                        self.world.read_resource::<Scores>().summary(),
+                        150,
+                        190,
+                    );
+                }
+                if self.session.is_some() {
+                    gui.draw_label(&mut buffer, "Click to go back to the menu", 90, 250);
+                } else {
+                    gui.draw_label(&mut buffer, "Click to play again!", 110, 250);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl<'a, 'b> EventHandler for Game<'a, 'b> {
+    fn update(&mut self, _ctx: &mut Context) {
+        // Check if another player joined our hosted game
+        if let Some((session, seed)) = self.lobby.accept() {
+            self.start_online(session, seed, true);
+        }
+
+        // Only draw what the other game sends when spectating
+        if let Some(spectator) = self.spectator.as_mut() {
+            if let Err(err) = spectator.update() {
+                self.spectator = None;
+                self.lobby = Lobby::Failed(format!("Stopped watching: {}", err));
+            }
+
+            return;
+        }
+
+        match self.session.take() {
+            Some(session) => self.tick_online(session),
+            None => self.tick(),
+        }
+
+        // Let the computer play when nobody touches the menu
+        let phase = (*self.world.read_resource::<Phase>()).clone();
+        if phase == Phase::Menu && !self.lobby.is_busy() && self.session.is_none() {
+            self.idle += 1;
+            if self.idle > ATTRACT_DELAY {
+                self.idle = 0;
+                self.start(GameMode::Autopilot);
+            }
+        } else if phase == Phase::GameOver && self.attracting() {
+            self.switch_phase(Phase::Menu);
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context) {
+        match self.spectator.as_ref() {
+            Some(spectator) => spectator.render(
+                &mut self.world.write_resource::<PixelBuffer>(),
+                &mut self.world.write_resource::<Gui>(),
+                &self.world.read_resource::<Palette>(),
+            ),
+            None => self.render_phase(),
+        }
+
+        // Get the pixel buffer to render it
+        let mut buffer = self.world.write_resource::<PixelBuffer>();
+
+        self.post_process.apply(&mut buffer);
+
+        // Render the buffer
+        if let Some(render) = self.render.as_mut() {
+            render.render(ctx, &buffer);
+        }
+
+        let offset = self.world.read_resource::<effect::Camera>().offset();
+        self.background.update();
+        self.background.copy(buffer.pixels_mut(), offset);
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        // Start the game
+        self.idle = 0;
+
+        let phase = (*self.world.read_resource::<Phase>()).clone();
+        if self.spectator.is_some() {
+            // Spectators can't play
+        } else if self.attracting() || (phase == Phase::Menu && !self.lobby.is_busy()) {
+            self.start(GameMode::Single);
+        } else if phase == Phase::GameOver && self.session.is_some() {
+            self.stop_online(None);
+        } else if phase == Phase::GameOver {
+            // Play again with the same amount of players
+            let mode = *self.world.read_resource::<GameMode>();
+            self.start(mode);
+        }
+
+        (*self.world.write_resource::<Input>()).handle_mouse_button(true);
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        (*self.world.write_resource::<Input>()).handle_mouse_button(false);
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        self.idle = 0;
+
+        // The screen effects and colors can be changed everywhere
+        if self.post_process.toggle(keycode) {
+            return;
+        }
+        if keycode == KeyCode::F6 {
+            let mut palette = self.world.write_resource::<Palette>();
+            *palette = Palette::new(palette.preset().next());
+
+            return;
+        }
+
+        // Stop the computer from playing and handle the key on the menu
+        if self.attracting() {
+            self.switch_phase(Phase::Menu);
+        }
+
+        let phase = (*self.world.read_resource::<Phase>()).clone();
+
+        if self.spectator.is_some() {
+            if keycode == KeyCode::Escape {
+                self.spectator = None;
+            }
+
+            return;
+        }
+
+        // Type the address of the host
+        if phase == Phase::Menu && self.lobby.is_busy() {
+            match keycode {
+                KeyCode::Backspace => self.lobby.backspace(),
+                KeyCode::Enter | KeyCode::KpEnter => {
+                    if let Some((session, seed)) = self.lobby.connect() {
+                        self.start_online(session, seed, false);
+                    } else if let Some(spectator) = self.lobby.spectate() {
+                        self.spectator = Some(spectator);
+                    }
+                }
+                KeyCode::Escape => self.lobby = Lobby::Idle,
+                _ => (),
+            }
+
+            return;
+        }
+
+        // Setup an online game
+        if phase == Phase::Menu {
+            match keycode {
+                KeyCode::H => self.lobby = Lobby::host(),
+                KeyCode::J => self.lobby = Lobby::join(),
+                KeyCode::V => self.lobby = Lobby::watch(),
+                KeyCode::E => self.switch_phase(Phase::Editor),
+                KeyCode::B => {
+                    self.spectator_server = match self.spectator_server.take() {
+                        // Stop broadcasting when it's pressed again
+                        Some(_) => None,
+                        None => match SpectatorServer::start() {
+                            Ok(server) => Some(server),
+                            Err(err) => {
+                                self.lobby = Lobby::Failed(format!("Could not broadcast: {}", err));
+
+                                None
+                            }
+                        },
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // Start the game with the chosen amount of players
+        if (phase == Phase::Menu || phase == Phase::GameOver) && self.session.is_none() {
+            let mode = match keycode {
+                KeyCode::Key1 => Some(GameMode::Single),
+                KeyCode::Key2 => Some(GameMode::Coop),
+                KeyCode::Key3 => Some(GameMode::Versus),
+                _ => None,
+            };
+            if let Some(mode) = mode {
+                self.start(mode);
+
+                // Don't let the key trigger anything in the game itself
+                return;
+            }
+        }
+
+        (*self.world.write_resource::<Input>()).handle_key(keycode, true);
+    }
+
+    fn char_event(
+        &mut self,
+        _ctx: &mut Context,
+        character: char,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        self.lobby.type_char(character);
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        (*self.world.write_resource::<Input>()).handle_key(keycode, false);
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        // Translate the screen position to our canvas position
+        let screen_size = ctx.screen_size();
+
+        let x = x / screen_size.0 * WIDTH as f32;
+        let y = y / screen_size.1 * HEIGHT as f32;
+
+        self.idle = 0;
+
+        (*self.world.write_resource::<Input>()).handle_mouse_move(x as i32, y as i32);
+    }
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    // Seed random when on Linux
+    fn srand(input: u32);
+}
+#[cfg(not(target_os = "linux"))]
+fn srand(_: u32) {}
+
+/// Let the computer play a number of games without a window and print the results.
+fn run_headless(games: u64) -> Result<()> {
+    let mut game = Game::headless()?;
+
+    let mut total_level = 0;
+    for seed in 0..games {
+        quad_rand::srand(seed);
+        game.start(GameMode::Autopilot);
+
+        let mut ticks = 0;
+        while *game.world.read_resource::<Phase>() != Phase::GameOver && ticks < HEADLESS_MAX_TICKS
+        {
+            game.tick();
+            ticks += 1;
+        }
+
+        println!(
+            "Game {}: level {}, scrap {}, {} seconds",
+            seed,
+            game.level,
+            game.world.read_resource::<Wallet>().money(),
+            ticks / 60
+        );
+        total_level += game.level;
+    }
+
+    if games > 0 {
+        println!("Average level {:.2}", total_level as f64 / games as f64);
+    }
+
+    Ok(())
+}
+
+/// Measure the time it takes to update and draw an amount of particles every frame.
+fn run_particle_benchmark(amount: usize) {
+    let mut particles = particle::Particles::default();
+    let mut buffer = PixelBuffer::new(WIDTH, HEIGHT);
+
+    // Live long enough to survive the whole benchmark
+    let emitter =
+        particle::ParticleEmitter::new(PARTICLE_BENCHMARK_FRAMES as f64 * 2.0, color::FOREGROUND)
+            .with_gradient(color::FOREGROUND, color::RED)
+            .with_fade()
+            .with_size(2.0, 1.0)
+            .with_drag(0.99);
+    for _ in 0..amount {
+        particles.spawn(
+            vek::Vec2::new(
+                random::range(0.0, WIDTH as f64),
+                random::range(0.0, HEIGHT as f64),
+            ),
+            vek::Vec2::new(random::range(-1.0, 1.0), random::range(-1.0, 1.0)),
+            &emitter,
+        );
+    }
+
+    let start = std::time::Instant::now();
+    for _ in 0..PARTICLE_BENCHMARK_FRAMES {
+        particles.update();
+        particles.render(&mut buffer, (0, 0));
+    }
+    let frame_time = start.elapsed().as_secs_f64() / PARTICLE_BENCHMARK_FRAMES as f64;
+
+    println!(
+        "{} particles: {:.3} ms per frame",
+        particles.len(),
+        frame_time * 1000.0
+    );
+}
+
+/// Start the game, or one of the tools picked with the command line arguments.
+pub fn run() {
+    unsafe {
+        srand(miniquad::date::now() as u32);
+    }
+
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        // Balance the game by letting the computer play: `ld46 --headless [games]`
+        Some("--headless") => {
+            let games = args
+                .next()
+                .and_then(|games| games.parse().ok())
+                .unwrap_or(10);
+
+            return run_headless(games).expect("Running headless failed");
+        }
+        // Measure the particle performance: `ld46 --particle-benchmark [amount]`
+        Some("--particle-benchmark") => {
+            let amount = args
+                .next()
+                .and_then(|amount| amount.parse().ok())
+                .unwrap_or(10_000);
+
+            return run_particle_benchmark(amount);
+        }
+        // Train agents over stdin & stdout: `ld46 --env [pixels|objects] [frame skip]`
+        Some("--env") => {
+            let kind = args
+                .next()
+                .and_then(|kind| env::ObservationKind::from_name(&kind))
+                .unwrap_or(env::ObservationKind::Objects);
+            let frame_skip = args.next().and_then(|skip| skip.parse().ok()).unwrap_or(1);
+
+            let env = env::Environment::new(kind)
+                .expect("Setting up environment failed")
+                .with_frame_skip(frame_skip);
+
+            return env::serve(env, std::io::stdin().lock(), std::io::stdout().lock())
+                .expect("Running environment failed");
+        }
+        _ => (),
+    }
+
+    miniquad::start(
+        Conf {
+            window_title: concat!("Fermi Paradox - ", env!("CARGO_PKG_VERSION")).to_string(),
+            window_width: WIDTH as i32 * 3,
+            window_height: HEIGHT as i32 * 3,
+            ..Default::default()
+        },
+        |ctx| Box::new(Game::new(ctx).expect("Setting up game state failed")),
+    );
+}
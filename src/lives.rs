@@ -1,9 +1,12 @@
-use crate::sprite;
+use crate::mask::Mask;
 use specs_blit::{blit::*, PixelBuffer};
-use sprite_gen::{MaskValue::*, Options};
 
 pub struct Lives {
     amount: u8,
+    /// The amount at the start of the game.
+    start: u8,
+    /// Heights where the planet got hit since they were last taken.
+    impacts: Vec<f64>,
     sprite: BlitBuffer,
 }
 
@@ -11,6 +14,8 @@ impl Lives {
     pub fn new(amount: u8) -> Self {
         Self {
             amount,
+            start: amount,
+            impacts: vec![],
             sprite: sprite(),
         }
     }
@@ -27,10 +32,31 @@ impl Lives {
         self.amount += 1;
     }
 
-    pub fn reduce(&mut self) {
+    /// Lose a life because something hit the planet at a height.
+    pub fn hit(&mut self, y: f64) {
         if self.amount > 0 {
             self.amount -= 1;
         }
+
+        self.impacts.push(y);
+    }
+
+    /// The heights of the hits since the last time this was called.
+    pub fn take_impacts(&mut self) -> Vec<f64> {
+        std::mem::take(&mut self.impacts)
+    }
+
+    /// Amount of lives less than at the start.
+    pub fn lost(&self) -> usize {
+        self.start.saturating_sub(self.amount) as usize
+    }
+
+    pub fn amount(&self) -> u8 {
+        self.amount
+    }
+
+    pub fn set_amount(&mut self, amount: u8) {
+        self.amount = amount;
     }
 
     pub fn is_dead(&self) -> bool {
@@ -39,26 +65,7 @@ impl Lives {
 }
 
 pub fn sprite() -> BlitBuffer {
-    let (width, _height, options) = (
-        5,
-        10,
-        Options {
-            mirror_x: true,
-            mirror_y: false,
-            colored: true,
-            edge_brightness: 0.24585259,
-            color_variations: 0.47232494,
-            brightness_noise: 0.81954944,
-            saturation: 1.0,
-            seed: quad_rand::rand() as u64,
-        },
-    );
-    let data = [
-        Empty, Empty, Solid, Solid, Empty, Empty, Solid, Body1, Body1, Solid, Solid, Body2, Body2,
-        Body1, Solid, Solid, Body2, Body1, Body1, Body1, Solid, Body2, Body2, Body1, Body1, Empty,
-        Solid, Body2, Body2, Body1, Empty, Empty, Solid, Body2, Body2, Empty, Empty, Empty, Solid,
-        Body2, Empty, Empty, Empty, Empty, Solid, Empty, Empty, Empty, Empty, Empty,
-    ];
-
-    sprite::buffer(width, options, &data)
+    Mask::load("life")
+        .expect("Could not load life mask")
+        .buffer()
 }
@@ -0,0 +1,350 @@
+use crate::sprite;
+use anyhow::{anyhow, bail, Result};
+use specs_blit::blit::BlitBuffer;
+use sprite_gen::{MaskValue, Options};
+use std::{fmt, fs, path::PathBuf};
+
+/// Directory the masks are saved to, relative to the crate so it doesn't depend on where the
+/// game is started. They are embedded from here when building.
+const MASK_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/masks");
+
+/// The masks of the sprites, embedded so they also work in the browser.
+const MASKS: [(&str, &str); 8] = [
+    ("small_ship", include_str!("../assets/masks/small_ship.txt")),
+    (
+        "medium_ship",
+        include_str!("../assets/masks/medium_ship.txt"),
+    ),
+    ("big_ship", include_str!("../assets/masks/big_ship.txt")),
+    ("paddle", include_str!("../assets/masks/paddle.txt")),
+    ("life", include_str!("../assets/masks/life.txt")),
+    (
+        "big_projectile",
+        include_str!("../assets/masks/big_projectile.txt"),
+    ),
+    (
+        "small_projectile",
+        include_str!("../assets/masks/small_projectile.txt"),
+    ),
+    ("missile", include_str!("../assets/masks/missile.txt")),
+];
+
+/// The shape of a generated sprite with the options to generate it.
+///
+/// Masks are written as ASCII art: `.` is empty, `#` solid, `1` and `2` the two kinds of body
+/// pixels. The rows are preceded by the options as `key: value` lines, the `size` written as
+/// `widthxheight` is required. Lines starting with `//` are ignored.
+#[derive(Debug, Clone)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    options: Options,
+    data: Vec<MaskValue>,
+    /// The comments at the top, kept when the mask is saved again.
+    comments: Vec<String>,
+}
+
+impl Mask {
+    /// Load one of the embedded masks.
+    pub fn load(name: &str) -> Result<Self> {
+        let (_, source) = MASKS
+            .iter()
+            .find(|(mask, _)| *mask == name)
+            .ok_or_else(|| anyhow!("Unknown mask \"{}\"", name))?;
+
+        Mask::parse(source).map_err(|err| anyhow!("Invalid mask \"{}\": {}", name, err))
+    }
+
+    /// The names of the embedded masks.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        MASKS.iter().map(|(name, _)| *name)
+    }
+
+    /// Read a mask from the ASCII format.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut size = None;
+        let mut options = Options::default();
+        let mut data = Vec::new();
+        let mut rows = 0;
+        let mut comments = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix("//") {
+                comments.push(comment.trim().to_string());
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                if rows > 0 {
+                    bail!("line {}: options must come before the rows", number);
+                }
+
+                let value = value.trim();
+                match key.trim() {
+                    "size" => size = Some(parse_size(value, number)?),
+                    "mirror_x" => options.mirror_x = parse_bool(value, number)?,
+                    "mirror_y" => options.mirror_y = parse_bool(value, number)?,
+                    "colored" => options.colored = parse_bool(value, number)?,
+                    "edge_brightness" => options.edge_brightness = parse_fraction(value, number)?,
+                    "color_variations" => options.color_variations = parse_fraction(value, number)?,
+                    "brightness_noise" => options.brightness_noise = parse_fraction(value, number)?,
+                    "saturation" => options.saturation = parse_fraction(value, number)?,
+                    other => bail!("line {}: unknown option \"{}\"", number, other),
+                }
+
+                continue;
+            }
+
+            let (width, _) = size.ok_or_else(|| anyhow!("the size must come before the rows"))?;
+            if line.chars().count() != width {
+                bail!(
+                    "line {}: row is {} pixels wide instead of {}",
+                    number,
+                    line.chars().count(),
+                    width
+                );
+            }
+
+            for pixel in line.chars() {
+                data.push(match pixel {
+                    '.' => MaskValue::Empty,
+                    '#' => MaskValue::Solid,
+                    '1' => MaskValue::Body1,
+                    '2' => MaskValue::Body2,
+                    other => bail!(
+                        "line {}: unknown pixel '{}', expected '.', '#', '1' or '2'",
+                        number,
+                        other
+                    ),
+                });
+            }
+            rows += 1;
+        }
+
+        let (width, height) = size.ok_or_else(|| anyhow!("the size is missing"))?;
+        if rows != height {
+            bail!("mask has {} rows instead of {}", rows, height);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            options,
+            data,
+            comments,
+        })
+    }
+
+    /// Write the mask to the asset directory, returns the path it's saved to.
+    pub fn save(&self, name: &str) -> Result<PathBuf> {
+        let path = PathBuf::from(MASK_DIR).join(format!("{}.txt", name));
+        fs::write(&path, self.to_string())?;
+
+        Ok(path)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the mask, the sprite is twice as high when it's mirrored vertically.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &MaskValue {
+        &self.data[y * self.width + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: MaskValue) {
+        self.data[y * self.width + x] = value;
+    }
+
+    /// Change the size, new pixels are empty and the pixels outside of it are removed.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+
+        self.data = (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                if x < self.width && y < self.height {
+                    self.pixel(x, y).clone()
+                } else {
+                    MaskValue::Empty
+                }
+            })
+            .collect();
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Generate a sprite with a new random seed.
+    pub fn buffer(&self) -> BlitBuffer {
+        self.buffer_with_seed(quad_rand::rand() as u64)
+    }
+
+    /// Generate a sprite, the same seed always gives the same sprite.
+    pub fn buffer_with_seed(&self, seed: u64) -> BlitBuffer {
+        sprite::buffer(
+            self.width,
+            Options {
+                seed,
+                ..self.options
+            },
+            &self.data,
+        )
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for comment in self.comments.iter() {
+            writeln!(f, "// {}", comment)?;
+        }
+
+        writeln!(f, "size: {}x{}", self.width, self.height)?;
+        writeln!(f, "mirror_x: {}", self.options.mirror_x)?;
+        writeln!(f, "mirror_y: {}", self.options.mirror_y)?;
+        writeln!(f, "colored: {}", self.options.colored)?;
+        writeln!(f, "edge_brightness: {}", self.options.edge_brightness)?;
+        writeln!(f, "color_variations: {}", self.options.color_variations)?;
+        writeln!(f, "brightness_noise: {}", self.options.brightness_noise)?;
+        writeln!(f, "saturation: {}", self.options.saturation)?;
+        writeln!(f)?;
+
+        for row in self.data.chunks_exact(self.width) {
+            let row = row
+                .iter()
+                .map(|pixel| match pixel {
+                    MaskValue::Empty => '.',
+                    MaskValue::Solid => '#',
+                    MaskValue::Body1 => '1',
+                    MaskValue::Body2 => '2',
+                })
+                .collect::<String>();
+            writeln!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a size written as `widthxheight`.
+fn parse_size(value: &str, number: usize) -> Result<(usize, usize)> {
+    value
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|(width, height)| *width > 0 && *height > 0)
+        .ok_or_else(|| {
+            anyhow!(
+                "line {}: invalid size \"{}\", expected something like \"10x8\"",
+                number,
+                value
+            )
+        })
+}
+
+fn parse_bool(value: &str, number: usize) -> Result<bool> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("line {}: \"{}\" is not true or false", number, value))
+}
+
+/// Parse a number between 0 and 1.
+fn parse_fraction(value: &str, number: usize) -> Result<f32> {
+    value
+        .parse()
+        .ok()
+        .filter(|fraction| (0.0..=1.0).contains(fraction))
+        .ok_or_else(|| anyhow!("line {}: \"{}\" is not between 0 and 1", number, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write and read the mask again like the editor does when saving.
+    fn reparse(mask: &Mask) -> Mask {
+        Mask::parse(&mask.to_string()).expect("Saved mask doesn't parse")
+    }
+
+    #[test]
+    fn embedded_masks_parse_back() {
+        for name in Mask::names() {
+            let mask = Mask::load(name).unwrap();
+            let saved = reparse(&mask);
+
+            assert_eq!(
+                (saved.width(), saved.height()),
+                (mask.width(), mask.height())
+            );
+            assert_eq!(saved.to_string(), mask.to_string(), "mask {}", name);
+        }
+    }
+
+    #[test]
+    fn edited_mask_parses_back() {
+        let mut mask = Mask::load("paddle").unwrap();
+        mask.resize(5, 3);
+        mask.set_pixel(4, 2, MaskValue::Body2);
+        mask.options_mut().mirror_x = true;
+        mask.options_mut().saturation = 0.25;
+
+        let saved = reparse(&mask);
+        assert_eq!((saved.width(), saved.height()), (5, 3));
+        assert_eq!(saved.pixel(4, 2), &MaskValue::Body2);
+        assert!(saved.options().mirror_x);
+        assert_eq!(saved.options().saturation, 0.25);
+        assert_eq!(saved.comments, mask.comments);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            (
+                "size: 2x1\n...",
+                "line 2: row is 3 pixels wide instead of 2",
+            ),
+            ("size: 2x2\n..", "mask has 1 rows instead of 2"),
+            ("size: 2x1\n.x", "line 2: unknown pixel 'x'"),
+            (
+                "size: 2x1\nshiny: true\n..",
+                "line 2: unknown option \"shiny\"",
+            ),
+            (
+                "size: 2x1\n..\ncolored: true",
+                "line 3: options must come before the rows",
+            ),
+            ("colored: true", "the size is missing"),
+            ("..", "the size must come before the rows"),
+            ("size: 2by1", "line 1: invalid size \"2by1\""),
+            ("size: 0x1", "line 1: invalid size \"0x1\""),
+            (
+                "size: 1x1\nmirror_x: yes\n.",
+                "line 2: \"yes\" is not true or false",
+            ),
+            (
+                "size: 1x1\nsaturation: 2\n.",
+                "line 2: \"2\" is not between 0 and 1",
+            ),
+        ];
+
+        for (source, expected) in cases.iter() {
+            let err = Mask::parse(source).unwrap_err().to_string();
+            assert!(err.contains(expected), "{:?} gave {:?}", source, err);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::player::Controller;
+
+/// How the game is played.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum GameMode {
+    /// A single paddle controlled with the mouse.
+    #[default]
+    Single,
+    /// Two paddles on the same machine, one controlled with the mouse and one with the keyboard.
+    Coop,
+    /// One paddle controlled with the mouse, the second player deploys the enemies with the
+    /// keyboard.
+    Versus,
+    /// Two paddles on different machines, both controlled with the mouse.
+    Online {
+        /// Whether this instance accepted the connection, the host controls the first paddle.
+        host: bool,
+    },
+    /// A single paddle controlled by the computer, for the attract mode and balancing.
+    Autopilot,
+}
+
+impl GameMode {
+    /// The controllers of the paddles that should be spawned, the index is the player id.
+    pub fn controllers(self) -> &'static [Controller] {
+        match self {
+            GameMode::Single | GameMode::Versus => &[Controller::Mouse],
+            GameMode::Coop => &[Controller::Mouse, Controller::Keyboard],
+            GameMode::Online { host: true } => &[Controller::Mouse, Controller::Remote],
+            GameMode::Online { host: false } => &[Controller::Remote, Controller::Mouse],
+            GameMode::Autopilot => &[Controller::Bot],
+        }
+    }
+
+    /// Amount of players defending the planet.
+    pub fn players(self) -> usize {
+        self.controllers().len()
+    }
+}
@@ -1,6 +1,65 @@
-use crate::{physics::*, random};
+use crate::{physics::*, player::Player, projectile::ProjectileEmitter, random};
 use specs_blit::specs::*;
 
+type Vec2 = vek::Vec2<f64>;
+
+/// The ways an enemy can move, every archetype picks one randomly from its own list.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Movement {
+    /// Keep the velocity it spawned with.
+    Straight,
+    Zigzag,
+    Homing,
+    Circling,
+    StopAndGo,
+    Dive,
+    Path,
+    Retreat,
+}
+
+impl Movement {
+    /// Add the velocity and the components needed for the movement.
+    pub fn insert(self, entity: Entity, updater: &LazyUpdate, speed_x: f64, speed_y: f64) {
+        match self {
+            Movement::Straight => updater.insert(entity, Velocity::new(-speed_x, speed_y)),
+            Movement::Zigzag => {
+                updater.insert(entity, Velocity::new(-speed_x, 0.0));
+                updater.insert(entity, Zigzag::new(speed_y, random::range(0.001, 0.2)));
+            }
+            Movement::Homing => {
+                updater.insert(entity, Velocity::new(-speed_x, 0.0));
+                updater.insert(entity, Homing::new(speed_x, 0.02));
+            }
+            Movement::Circling => {
+                // Circle clockwise or counter-clockwise
+                let angular_speed = if random::bool() { 0.03 } else { -0.03 };
+
+                updater.insert(entity, Velocity::new(-speed_x, 0.0));
+                updater.insert(
+                    entity,
+                    Circling::new(speed_x, random::range(20.0, 40.0), angular_speed),
+                );
+            }
+            Movement::StopAndGo => {
+                updater.insert(entity, Velocity::new(-speed_x, speed_y));
+                updater.insert(entity, StopAndGo::new(90.0, 60.0));
+            }
+            Movement::Dive => {
+                updater.insert(entity, Velocity::new(-speed_x, speed_y));
+                updater.insert(entity, Dive::new(random::range(150.0, 300.0), 0.05));
+            }
+            Movement::Path => {
+                updater.insert(entity, Velocity::new(-speed_x, 0.0));
+                updater.insert(entity, Path::swoop(speed_x));
+            }
+            Movement::Retreat => {
+                updater.insert(entity, Velocity::new(-speed_x, speed_y));
+                updater.insert(entity, Retreat::new(speed_x, 40.0));
+            }
+        }
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Zigzag {
     time: f64,
@@ -18,14 +77,266 @@ impl Zigzag {
     }
 }
 
+/// Steer towards the closest paddle.
+#[derive(Component, Debug)]
+pub struct Homing {
+    speed: f64,
+    /// How fast it can turn, between 0 and 1.
+    turn: f64,
+}
+
+impl Homing {
+    pub fn new(speed: f64, turn: f64) -> Self {
+        Self { speed, turn }
+    }
+}
+
+/// Fly in circles while moving forward.
+#[derive(Component, Debug)]
+pub struct Circling {
+    speed: f64,
+    radius: f64,
+    /// Radians per frame.
+    angular_speed: f64,
+    angle: f64,
+}
+
+impl Circling {
+    pub fn new(speed: f64, radius: f64, angular_speed: f64) -> Self {
+        Self {
+            speed,
+            radius,
+            angular_speed,
+            angle: random::range(0.0, std::f64::consts::TAU),
+        }
+    }
+}
+
+/// Alternate between moving and hovering in place.
+#[derive(Component, Debug)]
+pub struct StopAndGo {
+    move_time: f64,
+    stop_time: f64,
+    time: f64,
+    /// The velocity to continue with after stopping.
+    velocity: Option<Vec2>,
+}
+
+impl StopAndGo {
+    pub fn new(move_time: f64, stop_time: f64) -> Self {
+        Self {
+            move_time,
+            stop_time,
+            time: 0.0,
+            velocity: None,
+        }
+    }
+}
+
+/// Move normally until it's close enough and then accelerate into the planet.
+#[derive(Component, Debug)]
+pub struct Dive {
+    /// Start diving at this horizontal position.
+    start_x: f64,
+    acceleration: f64,
+}
+
+impl Dive {
+    pub fn new(start_x: f64, acceleration: f64) -> Self {
+        Self {
+            start_x,
+            acceleration,
+        }
+    }
+}
+
+/// Follow a Catmull-Rom spline through control points relative to the spawn position.
+#[derive(Component, Debug)]
+pub struct Path {
+    points: Vec<Vec2>,
+    /// Frames to move from one control point to the next.
+    segment_time: f64,
+    time: f64,
+    origin: Option<Vec2>,
+}
+
+impl Path {
+    pub fn new(points: Vec<Vec2>, segment_time: f64) -> Self {
+        Self {
+            points,
+            segment_time,
+            time: 0.0,
+            origin: None,
+        }
+    }
+
+    /// Swoop up or down and back while flying forward.
+    pub fn swoop(speed: f64) -> Self {
+        let (step, height) = (60.0 * speed, random::range(-60.0, 60.0));
+
+        Path::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(-step, height),
+                Vec2::new(-step * 2.0, -height),
+                Vec2::new(-step * 3.0, 0.0),
+                Vec2::new(-step * 4.0, height),
+            ],
+            60.0,
+        )
+    }
+
+    /// The position on the spline relative to the origin, nothing when the end is reached.
+    fn point(&self, time: f64) -> Option<Vec2> {
+        let segment = (time / self.segment_time) as usize;
+        if segment + 1 >= self.points.len() {
+            return None;
+        }
+
+        // Repeat the first and last points so the spline goes through all of them
+        let point =
+            |index: isize| self.points[index.clamp(0, self.points.len() as isize - 1) as usize];
+        let i = segment as isize;
+        let (p0, p1, p2, p3) = (point(i - 1), point(i), point(i + 1), point(i + 2));
+
+        let t = time / self.segment_time - segment as f64;
+        let (t2, t3) = (t * t, t * t * t);
+
+        Some(
+            ((p1 * 2.0)
+                + (p2 - p0) * t
+                + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+                + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+                * 0.5,
+        )
+    }
+}
+
+/// Fly backwards for a while after firing.
+#[derive(Component, Debug)]
+pub struct Retreat {
+    speed: f64,
+    duration: f64,
+    time_left: f64,
+}
+
+impl Retreat {
+    pub fn new(speed: f64, duration: f64) -> Self {
+        Self {
+            speed,
+            duration,
+            time_left: 0.0,
+        }
+    }
+}
+
 pub struct MovementSystem;
 impl<'a> System<'a> for MovementSystem {
-    type SystemData = (WriteStorage<'a, Velocity>, WriteStorage<'a, Zigzag>);
+    type SystemData = (
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, BoundingBox>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, ProjectileEmitter>,
+        WriteStorage<'a, Zigzag>,
+        ReadStorage<'a, Homing>,
+        WriteStorage<'a, Circling>,
+        WriteStorage<'a, StopAndGo>,
+        ReadStorage<'a, Dive>,
+        WriteStorage<'a, Path>,
+        WriteStorage<'a, Retreat>,
+    );
 
-    fn run(&mut self, (mut vel, mut zigzag): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            pos,
+            mut vel,
+            bb,
+            player,
+            emitter,
+            mut zigzag,
+            homing,
+            mut circling,
+            mut stop_and_go,
+            dive,
+            mut path,
+            mut retreat,
+        ): Self::SystemData,
+    ) {
         for (vel, zigzag) in (&mut vel, &mut zigzag).join() {
             zigzag.time += 1.0;
             vel.0.y = (zigzag.time * zigzag.time_div).sin() * zigzag.amount;
         }
+
+        let players = (&pos, &bb, &player)
+            .join()
+            .map(|(pos, bb, _)| pos.0 + bb.center_offset())
+            .collect::<Vec<_>>();
+        for (pos, vel, homing) in (&pos, &mut vel, &homing).join() {
+            let target = players
+                .iter()
+                .min_by(|a, b| a.distance(pos.0).total_cmp(&b.distance(pos.0)));
+            if let Some(target) = target {
+                let desired = (*target - pos.0).normalized() * homing.speed;
+                vel.0 = Vec2::lerp(vel.0, desired, homing.turn);
+            }
+        }
+
+        for (vel, circling) in (&mut vel, &mut circling).join() {
+            circling.angle += circling.angular_speed;
+
+            let tangent = circling.radius * circling.angular_speed;
+            vel.0 = Vec2::new(
+                -circling.speed - circling.angle.sin() * tangent,
+                circling.angle.cos() * tangent,
+            );
+        }
+
+        for (vel, stop_and_go) in (&mut vel, &mut stop_and_go).join() {
+            stop_and_go.time =
+                (stop_and_go.time + 1.0) % (stop_and_go.move_time + stop_and_go.stop_time);
+
+            if stop_and_go.time < stop_and_go.move_time {
+                if let Some(velocity) = stop_and_go.velocity.take() {
+                    vel.0 = velocity;
+                }
+            } else if stop_and_go.velocity.is_none() {
+                stop_and_go.velocity = Some(vel.0);
+                vel.0 = Vec2::zero();
+            }
+        }
+
+        for (pos, vel, dive) in (&pos, &mut vel, &dive).join() {
+            if pos.x < dive.start_x {
+                vel.x -= dive.acceleration;
+                // Stop bouncing around when diving
+                vel.y *= 0.9;
+            }
+        }
+
+        for (pos, vel, path) in (&pos, &mut vel, &mut path).join() {
+            let origin = *path.origin.get_or_insert(pos.0);
+
+            path.time += 1.0;
+            // Keep flying in the last direction when the path is done
+            if let Some(point) = path.point(path.time) {
+                // Move to the next point in a single frame
+                vel.0 = origin + point - pos.0;
+            }
+        }
+
+        for (vel, retreat, emitter) in (&mut vel, &mut retreat, (&emitter).maybe()).join() {
+            if emitter.is_some_and(ProjectileEmitter::fired) {
+                retreat.time_left = retreat.duration;
+            }
+
+            if retreat.time_left > 0.0 {
+                retreat.time_left -= 1.0;
+                vel.x = retreat.speed * 0.5;
+            } else {
+                vel.x = -retreat.speed;
+            }
+        }
     }
 }
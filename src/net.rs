@@ -0,0 +1,591 @@
+use crate::{
+    input::Input,
+    lives::Lives,
+    money::Wallet,
+    physics::{Position, Velocity},
+    spectate::{Spectator, SPECTATOR_PORT},
+};
+use anyhow::{anyhow, bail, Result};
+use miniquad::KeyCode;
+use specs_blit::specs::{Join, World, WorldExt};
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// The port used when hosting a game.
+pub const DEFAULT_PORT: u16 = 7046;
+
+/// How many ticks the local input is delayed, gives the packets time to arrive.
+const INPUT_DELAY: u32 = 3;
+/// Every this many ticks the state of the world is compared.
+const CHECKSUM_INTERVAL: u32 = 30;
+/// How long to wait for the host to respond when joining.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const MESSAGE_HELLO: u8 = 0;
+const MESSAGE_INPUT: u8 = 1;
+const MESSAGE_CHECKSUM: u8 = 2;
+
+/// The input of a single player for a single tick, this is what's sent over the network.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct TickInput {
+    mouse_x: i32,
+    mouse_y: i32,
+    mouse_down: bool,
+    /// Whether the dash key is held.
+    dash: bool,
+    /// Whether the barrier key is held.
+    barrier: bool,
+}
+
+impl TickInput {
+    /// Take the part of the input that's used in an online game.
+    pub fn from_input(input: &Input) -> Self {
+        Self {
+            mouse_x: input.mouse_x(),
+            mouse_y: input.mouse_y(),
+            mouse_down: input.mouse_down(),
+            dash: input.key_down(KeyCode::Q),
+            barrier: input.key_down(KeyCode::E),
+        }
+    }
+
+    /// Convert it back to input the systems can read, the keys that weren't held in the
+    /// previous tick are pressed.
+    pub fn to_input(self, previous: TickInput) -> Input {
+        let mut input = Input::default();
+        previous.apply(&mut input);
+        input.end_frame();
+        self.apply(&mut input);
+
+        input
+    }
+
+    /// Feed it to the input as if it were events.
+    fn apply(self, input: &mut Input) {
+        input.handle_mouse_move(self.mouse_x, self.mouse_y);
+        input.handle_mouse_button(self.mouse_down);
+        input.handle_key(KeyCode::Q, self.dash);
+        input.handle_key(KeyCode::E, self.barrier);
+    }
+}
+
+/// Everything that's sent between the two instances.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Message {
+    /// Sent by the host when the connection is accepted.
+    Hello { seed: u64 },
+    /// The input of the sending player for a tick.
+    Input { tick: u32, input: TickInput },
+    /// The hash of the world state of the sending player after a tick.
+    Checksum { tick: u32, checksum: u64 },
+}
+
+impl Message {
+    /// Append the binary representation to a buffer.
+    fn encode(self, buf: &mut Vec<u8>) {
+        match self {
+            Message::Hello { seed } => {
+                buf.push(MESSAGE_HELLO);
+                buf.extend_from_slice(&seed.to_le_bytes());
+            }
+            Message::Input { tick, input } => {
+                buf.push(MESSAGE_INPUT);
+                buf.extend_from_slice(&tick.to_le_bytes());
+                buf.extend_from_slice(&input.mouse_x.to_le_bytes());
+                buf.extend_from_slice(&input.mouse_y.to_le_bytes());
+                // All buttons packed as bits of a single byte
+                buf.push(
+                    input.mouse_down as u8 | (input.dash as u8) << 1 | (input.barrier as u8) << 2,
+                );
+            }
+            Message::Checksum { tick, checksum } => {
+                buf.push(MESSAGE_CHECKSUM);
+                buf.extend_from_slice(&tick.to_le_bytes());
+                buf.extend_from_slice(&checksum.to_le_bytes());
+            }
+        }
+    }
+
+    /// Try to read a message from the start of the buffer.
+    ///
+    /// Returns the message and the amount of bytes it took, or nothing if the message isn't
+    /// complete yet.
+    fn decode(buf: &[u8]) -> Result<Option<(Self, usize)>> {
+        let size = match buf.first() {
+            Some(&MESSAGE_HELLO) => 9,
+            Some(&MESSAGE_INPUT) => 14,
+            Some(&MESSAGE_CHECKSUM) => 13,
+            Some(other) => bail!("Received unknown message type {}", other),
+            None => return Ok(None),
+        };
+        if buf.len() < size {
+            return Ok(None);
+        }
+
+        let u32_at = |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+        let u64_at = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&buf[i..i + 8]);
+            u64::from_le_bytes(bytes)
+        };
+
+        let message = match buf[0] {
+            MESSAGE_HELLO => Message::Hello { seed: u64_at(1) },
+            MESSAGE_INPUT => Message::Input {
+                tick: u32_at(1),
+                input: TickInput {
+                    mouse_x: u32_at(5) as i32,
+                    mouse_y: u32_at(9) as i32,
+                    mouse_down: buf[13] & 1 != 0,
+                    dash: buf[13] & 2 != 0,
+                    barrier: buf[13] & 4 != 0,
+                },
+            },
+            _ => Message::Checksum {
+                tick: u32_at(1),
+                checksum: u64_at(5),
+            },
+        };
+
+        Ok(Some((message, size)))
+    }
+}
+
+/// Setting up a connection on the menu.
+#[derive(Debug, Default)]
+pub enum Lobby {
+    #[default]
+    Idle,
+    /// Waiting for another instance to join.
+    Hosting(TcpListener),
+    /// Typing the address of the host.
+    Joining(String),
+    /// Typing the address of a game to watch.
+    Watching(String),
+    /// The last attempt didn't work out.
+    Failed(String),
+}
+
+impl Lobby {
+    /// Start listening for another instance.
+    pub fn host() -> Self {
+        match TcpListener::bind(("0.0.0.0", DEFAULT_PORT)).and_then(|listener| {
+            listener.set_nonblocking(true)?;
+
+            Ok(listener)
+        }) {
+            Ok(listener) => Lobby::Hosting(listener),
+            Err(err) => Lobby::Failed(format!("Could not host: {}", err)),
+        }
+    }
+
+    /// Start typing the address to join, defaults to a host on the same machine.
+    pub fn join() -> Self {
+        Lobby::Joining(format!("127.0.0.1:{}", DEFAULT_PORT))
+    }
+
+    /// Start typing the address of a game to watch, defaults to a game on the same machine.
+    pub fn watch() -> Self {
+        Lobby::Watching(format!("127.0.0.1:{}", SPECTATOR_PORT))
+    }
+
+    /// Whether we are waiting for a connection or an address.
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self,
+            Lobby::Hosting(_) | Lobby::Joining(_) | Lobby::Watching(_)
+        )
+    }
+
+    /// Check if someone joined our hosted game, returns the session and the random seed.
+    pub fn accept(&mut self) -> Option<(Session, u64)> {
+        let stream = match self {
+            Lobby::Hosting(listener) => match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return None,
+                Err(err) => {
+                    *self = Lobby::Failed(format!("Could not accept: {}", err));
+                    return None;
+                }
+            },
+            _ => return None,
+        };
+
+        let seed = (miniquad::date::now() * 1000.0) as u64;
+        let result = stream
+            .set_nonblocking(false)
+            .map_err(Into::into)
+            .and_then(|_| Session::send_hello(&stream, seed))
+            .and_then(|_| Session::new(stream));
+
+        match result {
+            Ok(session) => {
+                *self = Lobby::Idle;
+
+                Some((session, seed))
+            }
+            Err(err) => {
+                *self = Lobby::Failed(format!("Could not accept: {}", err));
+
+                None
+            }
+        }
+    }
+
+    /// Connect to the typed address, returns the session and the random seed.
+    pub fn connect(&mut self) -> Option<(Session, u64)> {
+        let address = match self {
+            Lobby::Joining(address) => address.clone(),
+            _ => return None,
+        };
+
+        match Session::connect(&address) {
+            Ok(result) => {
+                *self = Lobby::Idle;
+
+                Some(result)
+            }
+            Err(err) => {
+                *self = Lobby::Failed(format!("Could not join {}: {}", address, err));
+
+                None
+            }
+        }
+    }
+
+    /// Connect to the typed address of a game that's broadcasting.
+    pub fn spectate(&mut self) -> Option<Spectator> {
+        let address = match self {
+            Lobby::Watching(address) => address.clone(),
+            _ => return None,
+        };
+
+        match Spectator::connect(&address) {
+            Ok(spectator) => {
+                *self = Lobby::Idle;
+
+                Some(spectator)
+            }
+            Err(err) => {
+                *self = Lobby::Failed(format!("Could not watch {}: {}", address, err));
+
+                None
+            }
+        }
+    }
+
+    /// Add a typed character to the address, only IPv4 addresses with a port are supported.
+    pub fn type_char(&mut self, character: char) {
+        if let Lobby::Joining(address) | Lobby::Watching(address) = self {
+            if character.is_ascii_digit() || character == '.' || character == ':' {
+                address.push(character);
+            }
+        }
+    }
+
+    /// Remove the last typed character from the address.
+    pub fn backspace(&mut self) {
+        if let Lobby::Joining(address) | Lobby::Watching(address) = self {
+            address.pop();
+        }
+    }
+
+    /// Text to show on the menu.
+    pub fn status(&self) -> String {
+        match self {
+            Lobby::Idle => {
+                "Press H to host or J to join an online game\nPress V to watch a game".to_string()
+            }
+            Lobby::Hosting(_) => format!("Waiting for a player on port {}...", DEFAULT_PORT),
+            Lobby::Joining(address) => format!("Join: {}_\nEnter to connect", address),
+            Lobby::Watching(address) => format!("Watch: {}_\nEnter to connect", address),
+            Lobby::Failed(err) => err.clone(),
+        }
+    }
+}
+
+/// A connection to another instance, both simulate the same world in lockstep.
+#[derive(Debug)]
+pub struct Session {
+    stream: TcpStream,
+    /// Received bytes that don't form a full message yet.
+    incoming: Vec<u8>,
+    /// Bytes that couldn't be written yet.
+    outgoing: Vec<u8>,
+    /// The next tick to simulate.
+    tick: u32,
+    /// The next tick to send the local input for.
+    sent: u32,
+    local_inputs: HashMap<u32, TickInput>,
+    remote_inputs: HashMap<u32, TickInput>,
+    /// The inputs of the last simulated tick, to know which keys were just pressed.
+    previous_inputs: (TickInput, TickInput),
+    local_checksums: HashMap<u32, u64>,
+    remote_checksums: HashMap<u32, u64>,
+    /// The first tick where the worlds were different.
+    desync: Option<u32>,
+}
+
+impl Session {
+    fn new(stream: TcpStream) -> Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+
+        // Nobody has sent anything for the first ticks yet, they are delayed
+        let empty = (0..INPUT_DELAY)
+            .map(|tick| (tick, TickInput::default()))
+            .collect::<HashMap<_, _>>();
+
+        Ok(Self {
+            stream,
+            incoming: vec![],
+            outgoing: vec![],
+            tick: 0,
+            sent: INPUT_DELAY,
+            local_inputs: empty.clone(),
+            remote_inputs: empty,
+            previous_inputs: Default::default(),
+            local_checksums: HashMap::new(),
+            remote_checksums: HashMap::new(),
+            desync: None,
+        })
+    }
+
+    fn connect(address: &str) -> Result<(Self, u64)> {
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("invalid address"))?;
+        let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+
+        // Wait for the host to tell us the seed
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        let mut buf = [0; 9];
+        stream.read_exact(&mut buf)?;
+        let seed = match Message::decode(&buf)? {
+            Some((Message::Hello { seed }, _)) => seed,
+            _ => bail!("host didn't say hello"),
+        };
+        stream.set_read_timeout(None)?;
+
+        Ok((Session::new(stream)?, seed))
+    }
+
+    fn send_hello(mut stream: &TcpStream, seed: u64) -> Result<()> {
+        let mut buf = vec![];
+        Message::Hello { seed }.encode(&mut buf);
+        stream.write_all(&buf)?;
+
+        Ok(())
+    }
+
+    /// Send the local input and get the inputs for the next tick when both are available.
+    ///
+    /// Returns the local and the remote input, or nothing when we are still waiting for the
+    /// other player.
+    pub fn advance(&mut self, live: &Input) -> Result<Option<(Input, Input)>> {
+        if self.sent <= self.tick + INPUT_DELAY {
+            let input = TickInput::from_input(live);
+            self.local_inputs.insert(self.sent, input);
+            self.send(Message::Input {
+                tick: self.sent,
+                input,
+            });
+            self.sent += 1;
+        }
+
+        self.flush()?;
+        self.receive()?;
+
+        if !self.remote_inputs.contains_key(&self.tick) {
+            return Ok(None);
+        }
+
+        let local = self.local_inputs.remove(&self.tick).unwrap_or_default();
+        let remote = self.remote_inputs.remove(&self.tick).unwrap_or_default();
+        self.tick += 1;
+
+        let (previous_local, previous_remote) = self.previous_inputs;
+        self.previous_inputs = (local, remote);
+
+        Ok(Some((
+            local.to_input(previous_local),
+            remote.to_input(previous_remote),
+        )))
+    }
+
+    /// Compare the state of the world after the last simulated tick with the other player.
+    pub fn verify(&mut self, checksum: u64) {
+        let tick = self.tick - 1;
+        if !tick.is_multiple_of(CHECKSUM_INTERVAL) {
+            return;
+        }
+
+        self.local_checksums.insert(tick, checksum);
+        self.send(Message::Checksum { tick, checksum });
+        self.compare(tick);
+    }
+
+    /// The first tick where the worlds didn't match anymore.
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    fn compare(&mut self, tick: u32) {
+        if let (Some(local), Some(remote)) = (
+            self.local_checksums.get(&tick),
+            self.remote_checksums.get(&tick),
+        ) {
+            if local != remote && self.desync.is_none() {
+                eprintln!("Desync detected at tick {}", tick);
+                self.desync = Some(tick);
+            }
+
+            self.local_checksums.remove(&tick);
+            self.remote_checksums.remove(&tick);
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        message.encode(&mut self.outgoing);
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => bail!("connection closed"),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<()> {
+        let mut buf = [0; 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => bail!("connection closed"),
+                Ok(read) => self.incoming.extend_from_slice(&buf[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        while let Some((message, size)) = Message::decode(&self.incoming)? {
+            self.incoming.drain(..size);
+
+            match message {
+                Message::Hello { .. } => bail!("received a second hello"),
+                Message::Input { tick, input } => {
+                    self.remote_inputs.insert(tick, input);
+                }
+                Message::Checksum { tick, checksum } => {
+                    self.remote_checksums.insert(tick, checksum);
+                    self.compare(tick);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash everything in the world that should be the same on both instances.
+pub fn checksum(world: &World) -> u64 {
+    // FNV-1a
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut add = |value: u64| {
+        for byte in value.to_le_bytes().iter() {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    let entities = world.entities();
+    let pos = world.read_storage::<Position>();
+    let vel = world.read_storage::<Velocity>();
+    for (entity, pos, vel) in (&entities, &pos, (&vel).maybe()).join() {
+        add(u64::from(entity.id()));
+        add(pos.x.to_bits());
+        add(pos.y.to_bits());
+        if let Some(vel) = vel {
+            add(vel.x.to_bits());
+            add(vel.y.to_bits());
+        }
+    }
+
+    if let Some(lives) = world.try_fetch::<Lives>() {
+        add(u64::from(lives.amount()));
+    }
+    add(world.read_resource::<Wallet>().money() as u64);
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::Hello {
+                seed: 0x0123_4567_89AB_CDEF,
+            },
+            Message::Input {
+                tick: 12345,
+                input: TickInput {
+                    mouse_x: -20,
+                    mouse_y: 150,
+                    mouse_down: true,
+                    dash: false,
+                    barrier: true,
+                },
+            },
+            Message::Checksum {
+                tick: u32::MAX,
+                checksum: u64::MAX - 1,
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for message in messages() {
+            let mut buf = Vec::new();
+            message.encode(&mut buf);
+            // The next message shouldn't be read along
+            buf.push(MESSAGE_HELLO);
+
+            assert_eq!(
+                Message::decode(&buf).unwrap(),
+                Some((message, buf.len() - 1))
+            );
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        for message in messages() {
+            let mut buf = Vec::new();
+            message.encode(&mut buf);
+
+            for size in 0..buf.len() {
+                assert_eq!(Message::decode(&buf[..size]).unwrap(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_type() {
+        assert!(Message::decode(&[0xFF, 0, 0, 0]).is_err());
+    }
+}
@@ -1,44 +1,198 @@
 use crate::{
-    entity::Lifetime,
+    effect::Camera,
     physics::{Position, Velocity},
+    random,
 };
 use specs_blit::{
-    specs::{
-        Component, DenseVecStorage, Entities, Join, LazyUpdate, NullStorage, Read, ReadStorage,
-        System,
-    },
-    Sprite, SpriteRef,
+    specs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, Write, WriteStorage},
+    PixelBuffer,
 };
 
 type Vec2 = vek::Vec2<f64>;
 
-/// A particle that moves around but doesn't collide.
-#[derive(Component, Debug, Default)]
-#[storage(NullStorage)]
-pub struct Particle;
+/// Frames per second the emission rate is based on.
+const FRAMES_PER_SECOND: f64 = 60.0;
+
+/// All living particles, stored as separate arrays outside of the ECS so thousands of them are
+/// cheap to update and draw.
+#[derive(Debug, Default)]
+pub struct Particles {
+    pos: Vec<Vec2>,
+    vel: Vec<Vec2>,
+    /// Frames since it was emitted.
+    age: Vec<f64>,
+    lifetime: Vec<f64>,
+    /// The colors at the start and the end of its life.
+    colors: Vec<(u32, u32)>,
+    /// Whether it becomes transparent towards the end of its life.
+    fade: Vec<bool>,
+    /// The width and height in pixels at the start and the end of its life.
+    size: Vec<(f64, f64)>,
+    gravity: Vec<Vec2>,
+    drag: Vec<f64>,
+}
+
+impl Particles {
+    /// Amount of living particles.
+    pub fn len(&self) -> usize {
+        self.pos.len()
+    }
+
+    /// Remove all particles.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Add a particle with the looks of the emitter.
+    pub fn spawn(&mut self, pos: Vec2, vel: Vec2, emitter: &ParticleEmitter) {
+        let jitter = random::range(-emitter.lifetime_jitter, emitter.lifetime_jitter);
+
+        self.pos.push(pos);
+        self.vel.push(vel);
+        self.age.push(0.0);
+        self.lifetime
+            .push((emitter.lifetime * (1.0 + jitter)).max(1.0));
+        self.colors.push(emitter.colors);
+        self.fade.push(emitter.fade);
+        self.size.push(emitter.size);
+        self.gravity.push(emitter.gravity);
+        self.drag.push(emitter.drag);
+    }
+
+    /// Age and move all particles, the ones at the end of their life are removed.
+    pub fn update(&mut self) {
+        let mut index = 0;
+        while index < self.len() {
+            self.age[index] += 1.0;
+            if self.age[index] >= self.lifetime[index] {
+                self.remove(index);
+                continue;
+            }
+
+            self.vel[index] = (self.vel[index] + self.gravity[index]) * self.drag[index];
+            self.pos[index] += self.vel[index];
+
+            index += 1;
+        }
+    }
+
+    /// How every particle looks on this frame.
+    pub fn dots(&self) -> impl Iterator<Item = Dot> + '_ {
+        (0..self.len()).map(move |index| {
+            let progress = (self.age[index] / self.lifetime[index]).min(1.0);
+            let (start, end) = self.colors[index];
+            let alpha = if self.fade[index] {
+                1.0 - progress
+            } else {
+                1.0
+            };
+
+            let (start_size, end_size) = self.size[index];
+            let size = (start_size + (end_size - start_size) * progress)
+                .round()
+                .max(1.0) as i32;
+            // Grow from the center
+            let pos = self.pos[index];
+
+            Dot {
+                x: pos.x as i32 - size / 2,
+                y: pos.y as i32 - size / 2,
+                size,
+                color: lerp_color(start, end, progress),
+                alpha,
+            }
+        })
+    }
+
+    /// Draw all particles directly into the buffer, moved by the offset.
+    pub fn render(&self, buffer: &mut PixelBuffer, offset: (i32, i32)) {
+        for dot in self.dots() {
+            dot.render(buffer, offset);
+        }
+    }
+
+    /// Remove a particle by moving the last one in its place.
+    fn remove(&mut self, index: usize) {
+        self.pos.swap_remove(index);
+        self.vel.swap_remove(index);
+        self.age.swap_remove(index);
+        self.lifetime.swap_remove(index);
+        self.colors.swap_remove(index);
+        self.fade.swap_remove(index);
+        self.size.swap_remove(index);
+        self.gravity.swap_remove(index);
+        self.drag.swap_remove(index);
+    }
+}
+
+/// A particle as it's drawn on a single frame.
+#[derive(Debug, Copy, Clone)]
+pub struct Dot {
+    /// The top left corner.
+    pub x: i32,
+    pub y: i32,
+    /// The width and height in pixels.
+    pub size: i32,
+    pub color: u32,
+    /// How much it covers what's behind it, between 0 and 1.
+    pub alpha: f64,
+}
+
+impl Dot {
+    /// Blend the square into the buffer, moved by the offset.
+    pub fn render(&self, buffer: &mut PixelBuffer, (offset_x, offset_y): (i32, i32)) {
+        let (width, height) = (buffer.width() as i32, buffer.height() as i32);
+        let pixels = buffer.pixels_mut();
+        let (x, y) = (self.x + offset_x, self.y + offset_y);
+
+        for pixel_y in y.max(0)..(y + self.size).min(height) {
+            for pixel_x in x.max(0)..(x + self.size).min(width) {
+                let pixel = &mut pixels[(pixel_y * width + pixel_x) as usize];
+                *pixel = lerp_color(*pixel, self.color, self.alpha);
+            }
+        }
+    }
+}
 
 /// A component that emits particles while it lives.
 #[derive(Component, Debug)]
 pub struct ParticleEmitter {
     /// Amount of particles to emit every second.
-    amount: u8,
+    rate: f64,
+    /// Part of a particle that wasn't emitted last frame.
+    pending: f64,
+    /// Emit this amount of particles at once and stop instead of emitting continuously.
+    burst: Option<usize>,
     /// How long the particle lifes.
     lifetime: f64,
+    /// The fraction the lifetime of every particle can randomly differ.
+    lifetime_jitter: f64,
     /// The maximum velocity of the particles.
     dispersion: f64,
-    /// The sprite to use.
-    sprite: SpriteRef,
+    colors: (u32, u32),
+    fade: bool,
+    size: (f64, f64),
+    gravity: Vec2,
+    /// Multiplier for the velocity every frame.
+    drag: f64,
     /// Optional offset.
     offset: Vec2,
 }
 
 impl ParticleEmitter {
-    pub fn new(lifetime: f64, sprite: SpriteRef) -> Self {
+    pub fn new(lifetime: f64, color: u32) -> Self {
         Self {
-            amount: 1,
+            rate: FRAMES_PER_SECOND,
+            pending: 0.0,
+            burst: None,
             dispersion: 0.5,
             lifetime,
-            sprite,
+            lifetime_jitter: 0.0,
+            colors: (color, color),
+            fade: false,
+            size: (1.0, 1.0),
+            gravity: Vec2::zero(),
+            drag: 1.0,
             offset: Vec2::new(0.0, 0.0),
         }
     }
@@ -55,38 +209,131 @@ impl ParticleEmitter {
         self
     }
 
-    pub fn with_amount(mut self, amount: u8) -> Self {
-        self.amount = amount;
+    /// Emit continuously with an amount of particles every second.
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+
+        self
+    }
+
+    /// Emit an amount of particles at once in the first frame and nothing after that.
+    pub fn with_burst(mut self, amount: usize) -> Self {
+        self.burst = Some(amount);
 
         self
     }
+
+    pub fn with_lifetime_jitter(mut self, jitter: f64) -> Self {
+        self.lifetime_jitter = jitter;
+
+        self
+    }
+
+    /// Change the color from the first to the second over the lifetime of the particles.
+    pub fn with_gradient(mut self, start: u32, end: u32) -> Self {
+        self.colors = (start, end);
+
+        self
+    }
+
+    /// Let the particles become transparent over their lifetime.
+    pub fn with_fade(mut self) -> Self {
+        self.fade = true;
+
+        self
+    }
+
+    /// Change the size from the first to the second over the lifetime of the particles.
+    pub fn with_size(mut self, start: f64, end: f64) -> Self {
+        self.size = (start, end);
+
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: Vec2) -> Self {
+        self.gravity = gravity;
+
+        self
+    }
+
+    pub fn with_drag(mut self, drag: f64) -> Self {
+        self.drag = drag;
+
+        self
+    }
+
+    /// The amount of particles to emit this frame.
+    fn emit(&mut self) -> usize {
+        if let Some(amount) = self.burst.take() {
+            // Only emit once
+            self.rate = 0.0;
+
+            return amount;
+        }
+
+        self.pending += self.rate / FRAMES_PER_SECOND;
+        let amount = self.pending.floor();
+        self.pending -= amount;
+
+        amount as usize
+    }
 }
 
 /// System that will spawn particles.
 pub struct ParticleEmitterSystem;
 impl<'a> System<'a> for ParticleEmitterSystem {
     type SystemData = (
-        Entities<'a>,
-        ReadStorage<'a, ParticleEmitter>,
+        Write<'a, Particles>,
+        WriteStorage<'a, ParticleEmitter>,
         ReadStorage<'a, Position>,
-        Read<'a, LazyUpdate>,
     );
 
-    fn run(&mut self, (entities, emitter, pos, updater): Self::SystemData) {
-        for (emitter, pos) in (&emitter, &pos).join() {
-            for _ in 0..emitter.amount {
-                // Spawn a new particle
-                let particle = entities.create();
-                updater.insert(particle, Particle);
-                // Set the destruction time of the particle
-                updater.insert(particle, Lifetime::new(emitter.lifetime));
-                // Clone the position of the emitter
-                updater.insert(particle, pos.add_offset(emitter.offset));
-                // Add a new random velocity
-                updater.insert(particle, Velocity::from_random_range(emitter.dispersion));
-                // Use the sprite reference of the emitter
-                updater.insert(particle, Sprite::new(emitter.sprite.clone()));
+    fn run(&mut self, (mut particles, mut emitter, pos): Self::SystemData) {
+        for (emitter, pos) in (&mut emitter, &pos).join() {
+            for _ in 0..emitter.emit() {
+                particles.spawn(
+                    pos.0 + emitter.offset,
+                    Velocity::from_random_range(emitter.dispersion).0,
+                    emitter,
+                );
             }
         }
     }
 }
+
+/// System that ages and moves the particles.
+pub struct ParticleSystem;
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = Write<'a, Particles>;
+
+    fn run(&mut self, mut particles: Self::SystemData) {
+        particles.update();
+    }
+}
+
+/// System that draws the particles directly into the buffer, before the sprites are rendered.
+pub struct ParticleRenderSystem;
+impl<'a> System<'a> for ParticleRenderSystem {
+    type SystemData = (
+        Write<'a, PixelBuffer>,
+        Read<'a, Camera>,
+        Read<'a, Particles>,
+    );
+
+    fn run(&mut self, (mut buffer, camera, particles): Self::SystemData) {
+        particles.render(&mut buffer, camera.offset());
+    }
+}
+
+/// Interpolate every color channel separately.
+fn lerp_color(from: u32, to: u32, amount: f64) -> u32 {
+    (0..4).fold(0, |color, channel| {
+        let shift = channel * 8;
+        let (from, to) = (
+            ((from >> shift) & 0xFF) as f64,
+            ((to >> shift) & 0xFF) as f64,
+        );
+
+        color | (((from + (to - from) * amount).round() as u32) << shift)
+    })
+}
@@ -0,0 +1,175 @@
+use crate::{physics::*, player::Player, random};
+use specs_blit::specs::*;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+type Vec2 = vek::Vec2<f64>;
+
+/// Where a pattern aims.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Target {
+    /// The closest paddle.
+    Paddle,
+    /// The center of the planet.
+    Planet,
+}
+
+/// How a `ProjectileEmitter` fires its projectiles.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Pattern {
+    /// A single projectile with a random vertical velocity within the spread.
+    #[default]
+    Single,
+    /// A single projectile flying towards the target.
+    Aimed(Target),
+    /// Projectiles aimed at the paddle in quick succession.
+    Burst { shots: usize, delay: f64 },
+    /// Projectiles evenly spread over a half circle facing the planet.
+    Ring { amount: usize },
+    /// Rotating arms, only the ones facing the planet fire.
+    Spiral { arms: usize, step: f64 },
+    /// Projectiles evenly spread over an angle around the paddle.
+    Fan { amount: usize, angle: f64 },
+    /// A slowly drifting projectile that launches itself at the paddle after a delay.
+    Mine { delay: f64 },
+    /// A projectile that steers towards the paddle until it runs out of fuel.
+    Missile { fuel: f64 },
+    /// A single projectile flying straight to the right, used by the guns of the paddles.
+    Forward,
+    /// A beam over the lane in front of the enemy, it's shown before it fires and sweeps towards
+    /// the paddle while firing.
+    Laser {
+        warmup: f64,
+        duration: f64,
+        sweep: f64,
+    },
+}
+
+impl Pattern {
+    /// Multiplier for the time between volleys, patterns firing many projectiles fire less often.
+    pub fn interval_factor(self) -> f64 {
+        match self {
+            Pattern::Spiral { .. } => 0.1,
+            Pattern::Ring { .. } => 1.5,
+            Pattern::Fan { .. } => 1.2,
+            Pattern::Laser { .. } => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    /// The velocities of the projectiles in a single volley, or the sweeping velocity of a laser.
+    ///
+    /// The spiral angle is updated every volley.
+    pub fn volley(
+        self,
+        speed: f64,
+        spread: f64,
+        origin: Vec2,
+        paddle: Option<Vec2>,
+        spiral_angle: &mut f64,
+    ) -> Vec<Vec2> {
+        let aim = |target: Target| {
+            let target = match (target, paddle) {
+                (Target::Paddle, Some(paddle)) => paddle,
+                _ => Vec2::new(0.0, crate::HEIGHT as f64 / 2.0),
+            };
+            let dir = target - origin;
+
+            dir.y.atan2(dir.x)
+        };
+        let from_angle = |angle: f64| Vec2::new(angle.cos(), angle.sin()) * speed;
+
+        match self {
+            Pattern::Single => vec![Vec2::new(-speed, random::range(-spread, spread))],
+            Pattern::Aimed(target) => vec![from_angle(aim(target))],
+            // The burst itself is handled by the emitter, every shot is aimed
+            Pattern::Burst { .. } => vec![from_angle(aim(Target::Paddle))],
+            Pattern::Ring { amount } => (0..amount)
+                .map(|index| from_angle(FRAC_PI_2 + PI * (index + 1) as f64 / (amount + 1) as f64))
+                .collect(),
+            Pattern::Spiral { arms, step } => {
+                *spiral_angle = (*spiral_angle + step) % TAU;
+
+                (0..arms)
+                    .map(|arm| *spiral_angle + arm as f64 * TAU / arms as f64)
+                    // Don't shoot backwards into the other enemies
+                    .filter(|angle| angle.cos() < -0.2)
+                    .map(from_angle)
+                    .collect()
+            }
+            Pattern::Fan { amount, angle } => {
+                let center = aim(Target::Paddle);
+
+                (0..amount)
+                    .map(|index| {
+                        let offset = index as f64 / (amount.max(2) - 1) as f64 - 0.5;
+
+                        from_angle(center + offset * angle)
+                    })
+                    .collect()
+            }
+            // Drift slowly until the mine launches
+            Pattern::Mine { .. } => vec![Vec2::new(-0.2, 0.0)],
+            Pattern::Missile { .. } => vec![from_angle(aim(Target::Paddle))],
+            Pattern::Forward => vec![Vec2::new(speed, 0.0)],
+            // Lasers don't fly, they only move up or down
+            Pattern::Laser { sweep, .. } => {
+                let target = paddle.map_or(origin.y, |paddle| paddle.y);
+
+                vec![Vec2::new(0.0, (target - origin.y).signum() * sweep)]
+            }
+        }
+    }
+}
+
+/// A projectile that's waiting to launch itself at the paddle.
+#[derive(Component, Debug)]
+pub struct Mine {
+    delay: f64,
+    speed: f64,
+}
+
+impl Mine {
+    pub fn new(delay: f64, speed: f64) -> Self {
+        Self { delay, speed }
+    }
+}
+
+/// System that launches the mines.
+pub struct MineSystem;
+impl<'a> System<'a> for MineSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Mine>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, BoundingBox>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, mut mine, player, pos, mut vel, bb, updater): Self::SystemData) {
+        let paddles = (&player, &pos, &bb)
+            .join()
+            .map(|(_, pos, bb)| pos.0 + bb.center_offset())
+            .collect::<Vec<_>>();
+
+        for (entity, mine, pos, vel) in (&*entities, &mut mine, &pos, &mut vel).join() {
+            mine.delay -= 1.0;
+            if mine.delay > 0.0 {
+                continue;
+            }
+
+            let target = paddles
+                .iter()
+                .min_by(|a, b| a.distance(pos.0).total_cmp(&b.distance(pos.0)))
+                .copied()
+                .unwrap_or_else(|| Vec2::new(0.0, pos.y));
+            let dir = (target - pos.0).normalized();
+
+            // Always launch towards the planet so it can be reflected
+            vel.0 = Vec2::new(dir.x.min(-0.5), dir.y).normalized() * mine.speed;
+
+            updater.remove::<Mine>(entity);
+        }
+    }
+}
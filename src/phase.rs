@@ -6,6 +6,8 @@ pub enum Phase {
     Play,
     WaitingForLastEnemy,
     GameOver,
+    /// Painting sprite masks.
+    Editor,
     SwitchTo(Box<Phase>),
 }
 
@@ -32,6 +32,10 @@ impl Velocity {
         Self(Vec2::new(x, y))
     }
 
+    pub fn from_vec2(v: Vec2) -> Self {
+        Self(v)
+    }
+
     /// Construct a new velocity where the X and Y velocity are randomly placed inside the supplied
     /// range.
     pub fn from_random_range(range: f64) -> Self {
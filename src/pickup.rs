@@ -1,14 +1,23 @@
 use crate::{
-    color,
+    color::Palette,
     effect::ScreenFlash,
     entity::Lifetime,
     lives,
     lives::Lives,
+    particle::ParticleEmitter,
     physics::{BoundingBox, Position, Velocity},
     projectile::Projectile,
-    random,
+    random, sprite,
+    sprite::{Animation, SpriteRef},
 };
-use specs_blit::{specs::*, Sprite, SpriteRef};
+use specs_blit::specs::*;
+
+type Vec2 = vek::Vec2<f64>;
+
+/// Frames of the spinning pickups.
+const SPIN_FRAMES: usize = 12;
+/// Frames of the spinning animation shown every second.
+const SPIN_FRAME_RATE: f64 = 12.0;
 
 #[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Pickup {
@@ -19,7 +28,7 @@ pub enum Pickup {
 pub struct PickupEmitter {
     current_time: f64,
     interval: f64,
-    health_sprite: SpriteRef,
+    health_frames: Vec<SpriteRef>,
 }
 
 impl PickupEmitter {
@@ -27,13 +36,19 @@ impl PickupEmitter {
         Self {
             interval: random::range(15.0 * 60.0, 40.0 * 60.0),
             current_time: 0.0,
-            health_sprite: specs_blit::load(lives::sprite()).expect("Could not create sprite"),
+            health_frames: sprite::spin(lives::sprite(), SPIN_FRAMES)
+                .expect("Could not create sprite"),
         }
     }
 
-    pub fn sprite(&self, type_: Pickup) -> SpriteRef {
+    /// Frames of the spinning health pickup.
+    pub fn health_frames(&self) -> &[SpriteRef] {
+        &self.health_frames
+    }
+
+    pub fn animation(&self, type_: Pickup) -> Animation {
         match type_ {
-            Pickup::Health => self.health_sprite.clone(),
+            Pickup::Health => Animation::new(self.health_frames.clone(), SPIN_FRAME_RATE),
         }
     }
 }
@@ -43,11 +58,12 @@ pub struct PickupEmitterSystem;
 impl<'a> System<'a> for PickupEmitterSystem {
     type SystemData = (
         Entities<'a>,
+        Read<'a, Palette>,
         WriteStorage<'a, PickupEmitter>,
         Read<'a, LazyUpdate>,
     );
 
-    fn run(&mut self, (entities, mut emitter, updater): Self::SystemData) {
+    fn run(&mut self, (entities, palette, mut emitter, updater): Self::SystemData) {
         for mut emitter in (&mut emitter).join() {
             emitter.current_time += 1.0;
             if emitter.current_time >= emitter.interval {
@@ -65,8 +81,23 @@ impl<'a> System<'a> for PickupEmitterSystem {
                     ),
                 );
                 updater.insert(pickup, Velocity::new(-0.5, 0.0));
-                updater.insert(pickup, Sprite::new(emitter.sprite(type_)));
+                let animation = emitter.animation(type_);
+                updater.insert(pickup, animation.first().sprite());
+                updater.insert(pickup, animation.first().id());
+                updater.insert(pickup, animation);
                 updater.insert(pickup, BoundingBox::new(10.0, 10.0));
+                // Sparkles rising from the pickup
+                updater.insert(
+                    pickup,
+                    ParticleEmitter::new(30.0, palette.heal)
+                        .with_rate(20.0)
+                        .with_gradient(palette.heal, palette.ui)
+                        .with_fade()
+                        .with_lifetime_jitter(0.5)
+                        .with_dispersion(0.2)
+                        .with_gravity(Vec2::new(0.0, -0.02))
+                        .with_offset(Vec2::new(5.0, 5.0)),
+                );
             }
         }
     }
@@ -76,6 +107,7 @@ pub struct PickupSystem;
 impl<'a> System<'a> for PickupSystem {
     type SystemData = (
         Entities<'a>,
+        Read<'a, Palette>,
         Option<Write<'a, Lives>>,
         ReadStorage<'a, Pickup>,
         ReadStorage<'a, Projectile>,
@@ -84,7 +116,10 @@ impl<'a> System<'a> for PickupSystem {
         Read<'a, LazyUpdate>,
     );
 
-    fn run(&mut self, (entities, lives, pickup, projectile, pos, bb, updater): Self::SystemData) {
+    fn run(
+        &mut self,
+        (entities, palette, lives, pickup, projectile, pos, bb, updater): Self::SystemData,
+    ) {
         for (entity, pos, _) in (&*entities, &pos, &pickup).join() {
             if pos.0.x <= 0.0 {
                 let _ = entities.delete(entity);
@@ -105,9 +140,24 @@ impl<'a> System<'a> for PickupSystem {
                         lives.increase();
 
                         let flash = entities.create();
-                        updater.insert(flash, ScreenFlash::new(color::GREEN));
+                        updater.insert(flash, ScreenFlash::new(palette.heal));
                         updater.insert(flash, Lifetime::new(3.0));
 
+                        let burst = entities.create();
+                        updater.insert(
+                            burst,
+                            ParticleEmitter::new(20.0, palette.heal)
+                                .with_burst(24)
+                                .with_gradient(palette.ui, palette.heal)
+                                .with_fade()
+                                .with_size(2.0, 1.0)
+                                .with_dispersion(2.0)
+                                .with_drag(0.9)
+                                .with_offset(pickup_bb.center_offset()),
+                        );
+                        updater.insert(burst, pickup_pos.clone());
+                        updater.insert(burst, Lifetime::new(2.0));
+
                         let _ = entities.delete(projectile_entity);
                         let _ = entities.delete(pickup_entity);
                     }
@@ -0,0 +1,186 @@
+use crate::{
+    color::{self, Palette},
+    effect::Camera,
+    entity::Lifetime,
+    lives::Lives,
+    particle::ParticleEmitter,
+    physics::Position,
+};
+use specs_blit::{specs::*, PixelBuffer};
+
+type Vec2 = vek::Vec2<f64>;
+
+/// Width of the planet sprite, everything left of it is darkened when it's damaged.
+const PLANET_WIDTH: usize = 6;
+/// Vertical radius of a crater in pixels.
+const CRATER_RADIUS: i32 = 4;
+/// How much darker the planet becomes for every lost life.
+const DARKEN_PER_LIFE: f64 = 0.2;
+/// The planet never gets darker than this.
+const MIN_BRIGHTNESS: f64 = 0.3;
+
+/// A hole burned into the planet where it got hit.
+#[derive(Debug)]
+struct Crater {
+    y: f64,
+    /// The emitter of the flames coming out of it.
+    fire: Entity,
+}
+
+/// The planet on the left side of the screen, gets damaged when lives are lost.
+#[derive(Component, Debug, Default)]
+pub struct Planet {
+    craters: Vec<Crater>,
+}
+
+impl Planet {
+    /// The heights of the craters.
+    pub fn craters(&self) -> impl Iterator<Item = f64> + '_ {
+        self.craters.iter().map(|crater| crater.y)
+    }
+}
+
+/// System that adds craters where the planet got hit and removes them when it's healed.
+pub struct PlanetSystem;
+impl<'a> System<'a> for PlanetSystem {
+    type SystemData = (
+        Entities<'a>,
+        Option<Write<'a, Lives>>,
+        Read<'a, Palette>,
+        WriteStorage<'a, Planet>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, lives, palette, mut planet, updater): Self::SystemData) {
+        let mut lives = match lives {
+            Some(lives) => lives,
+            None => return,
+        };
+
+        let impacts = lives.take_impacts();
+        for planet in (&mut planet).join() {
+            for y in impacts.iter() {
+                let pos = Position::new(0.0, *y);
+
+                let impact = entities.create();
+                updater.insert(
+                    impact,
+                    ParticleEmitter::new(20.0, palette.danger)
+                        .with_burst(30)
+                        .with_gradient(palette.ui, palette.danger)
+                        .with_fade()
+                        .with_size(2.0, 1.0)
+                        .with_dispersion(2.5)
+                        .with_drag(0.9)
+                        .with_lifetime_jitter(0.5),
+                );
+                updater.insert(impact, pos.clone());
+                updater.insert(impact, Lifetime::new(2.0));
+
+                // Flames rising away from the planet as long as the crater exists
+                let fire = entities.create();
+                updater.insert(
+                    fire,
+                    ParticleEmitter::new(25.0, palette.explosion)
+                        .with_rate(20.0)
+                        .with_gradient(palette.explosion, palette.danger)
+                        .with_fade()
+                        .with_dispersion(0.3)
+                        .with_gravity(Vec2::new(0.02, 0.0))
+                        .with_lifetime_jitter(0.5),
+                );
+                updater.insert(fire, pos);
+
+                planet.craters.push(Crater { y: *y, fire });
+            }
+
+            // Picking up health repairs the latest damage
+            while planet.craters.len() > lives.lost() {
+                let crater = planet.craters.pop().expect("Craters are empty");
+                let _ = entities.delete(crater.fire);
+
+                let heal = entities.create();
+                updater.insert(
+                    heal,
+                    ParticleEmitter::new(25.0, palette.heal)
+                        .with_burst(20)
+                        .with_gradient(palette.heal, palette.ui)
+                        .with_fade()
+                        .with_dispersion(1.0)
+                        .with_drag(0.95),
+                );
+                updater.insert(heal, Position::new(0.0, crater.y));
+                updater.insert(heal, Lifetime::new(2.0));
+            }
+        }
+    }
+}
+
+/// System that draws the damage on top of the planet sprite.
+pub struct PlanetRenderSystem;
+impl<'a> System<'a> for PlanetRenderSystem {
+    type SystemData = (
+        Write<'a, PixelBuffer>,
+        Read<'a, Camera>,
+        Option<Read<'a, Lives>>,
+        ReadStorage<'a, Planet>,
+    );
+
+    fn run(&mut self, (mut buffer, camera, lives, planet): Self::SystemData) {
+        let lost = match lives {
+            Some(lives) => lives.lost(),
+            None => return,
+        };
+
+        for planet in planet.join() {
+            render_damage(&mut buffer, camera.offset(), lost, planet.craters());
+        }
+    }
+}
+
+/// Draw the damage of the planet on top of its sprite: darker for every lost life with a crater
+/// at every height.
+pub fn render_damage<I: Iterator<Item = f64>>(
+    buffer: &mut PixelBuffer,
+    (offset_x, offset_y): (i32, i32),
+    lost: usize,
+    craters: I,
+) {
+    // Follow the planet sprite when the screen shakes
+    let width = buffer.width();
+    let planet_x = offset_x.max(0) as usize;
+    let planet_width = (PLANET_WIDTH as i32 + offset_x).max(0) as usize;
+    let pixels = buffer.pixels_mut();
+
+    // The atmosphere darkens with every lost life
+    if lost > 0 {
+        let brightness = (1.0 - lost as f64 * DARKEN_PER_LIFE).max(MIN_BRIGHTNESS);
+        for row in pixels.chunks_exact_mut(width) {
+            for pixel in row.iter_mut().take(planet_width).skip(planet_x) {
+                *pixel = color::dim(*pixel, brightness);
+            }
+        }
+    }
+
+    for crater_y in craters {
+        for dy in -CRATER_RADIUS..=CRATER_RADIUS {
+            let y = crater_y as i32 + dy + offset_y;
+            if y < 0 || y >= crate::HEIGHT as i32 {
+                continue;
+            }
+
+            // Round, and darkest in the center
+            let radius = CRATER_RADIUS - dy.abs();
+            for x in 0..radius.min(PLANET_WIDTH as i32) {
+                let screen_x = x + offset_x;
+                if screen_x < 0 {
+                    continue;
+                }
+
+                let pixel = &mut pixels[y as usize * width + screen_x as usize];
+                let depth = (x + dy.abs()) as f64 / CRATER_RADIUS as f64;
+                *pixel = color::dim(*pixel, 0.2 + depth * 0.5);
+            }
+        }
+    }
+}
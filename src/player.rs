@@ -1,89 +1,355 @@
-use crate::{input::Input, physics::*, sprite};
+use crate::{
+    ability::{Barrier, Dash},
+    color::Palette,
+    gui,
+    input::{Input, RemoteInput},
+    pattern::Pattern,
+    physics::*,
+    projectile::ProjectileEmitter,
+    sprite::{Sprites, SHOT_HEIGHT, SHOT_WIDTH},
+    upgrade::{HoldProjectile, Upgrades},
+};
 use anyhow::Result;
+use miniquad::KeyCode;
 use specs_blit::{
     specs::{
-        Builder, Component, Join, NullStorage, Read, ReadStorage, System, World, WorldExt,
+        Builder, Component, DenseVecStorage, Join, Read, ReadStorage, System, World, WorldExt,
         WriteStorage,
     },
-    Sprite,
+    PixelBuffer,
 };
-use sprite_gen::{MaskValue::*, Options};
 
 const PLAYER_SPEED: f64 = 0.5;
+/// Acceleration of the paddles steered with keys, they can't jump to a spot like a cursor.
+const KEYBOARD_SPEED: f64 = 1.0;
 const PLAYER_DRAG: f64 = 0.85;
 
+/// Horizontal distance between the paddles of different players.
+const PLAYER_SPACING: f64 = 16.0;
+
+/// Frames between the shots of the gun.
+const GUN_COOLDOWN: f64 = 20.0;
+const GUN_SPEED: f64 = 4.0;
+const GUN_MAX_ENERGY: f64 = 100.0;
+/// Energy used by every shot.
+const GUN_SHOT_ENERGY: f64 = 30.0;
+/// Energy regained every frame.
+const GUN_RECHARGE: f64 = 0.2;
+/// Frames the button needs to be held to fully charge the paddle.
+const CHARGE_TIME: f64 = 60.0;
+/// Frames a full charge lasts after releasing the button.
+const CHARGE_DECAY_TIME: f64 = 30.0;
+/// Charges below this are lost when reflecting, so a quick click doesn't count.
+const CHARGE_MIN: f64 = 0.2;
+/// Extra speed of a fully charged reflection, relative to the original speed.
+const CHARGE_SPEED_BONUS: f64 = 1.0;
+/// Extra enemies a fully charged reflection can go through.
+const CHARGE_DAMAGE_BONUS: f64 = 2.0;
+/// Width of the charge meter next to the paddle.
+const CHARGE_METER_WIDTH: usize = 2;
+
+/// Size of the energy meter on the HUD.
+const GUN_METER_WIDTH: usize = 40;
+const GUN_METER_HEIGHT: usize = 3;
+
+/// The input device that moves a paddle.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Controller {
+    /// Follow the vertical mouse position, hold with a mouse button.
+    Mouse,
+    /// Move with W/S or the arrow keys, hold with space.
+    Keyboard,
+    /// Follow the mouse of the other player in an online game.
+    Remote,
+    /// Moved by the `AutopilotSystem`, never holds projectiles.
+    Bot,
+}
+
+impl Controller {
+    /// Whether the button to hold projectiles is pressed.
+    pub fn holding(self, input: &Input, remote: &RemoteInput) -> bool {
+        match self {
+            Controller::Mouse => input.mouse_down(),
+            Controller::Keyboard => input.key_down(KeyCode::Space),
+            Controller::Remote => remote.mouse_down(),
+            Controller::Bot => false,
+        }
+    }
+
+    /// Whether the button to fire the gun is pressed.
+    pub fn firing(self, input: &Input, remote: &RemoteInput) -> bool {
+        match self {
+            Controller::Mouse => input.mouse_down(),
+            Controller::Keyboard => input.key_down(KeyCode::F) || input.key_down(KeyCode::Enter),
+            Controller::Remote => remote.mouse_down(),
+            Controller::Bot => false,
+        }
+    }
+
+    /// The vertical direction the keyboard wants to move in.
+    pub fn keyboard_direction(input: &Input) -> f64 {
+        let up = input.key_down(KeyCode::W) || input.key_down(KeyCode::Up);
+        let down = input.key_down(KeyCode::S) || input.key_down(KeyCode::Down);
+
+        match (up, down) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
 /// Component to set something as controllable.
+#[derive(Component, Debug)]
+pub struct Player {
+    /// Which player owns this paddle, starts at 0.
+    id: u8,
+    controller: Controller,
+}
+
+impl Player {
+    pub fn new(id: u8, controller: Controller) -> Self {
+        Self { id, controller }
+    }
+
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub fn controller(&self) -> Controller {
+        self.controller
+    }
+}
+
+/// A gun on the paddle that fires a projectile every click, as long as there's enough energy.
+#[derive(Component, Debug)]
+pub struct Gun {
+    energy: f64,
+    /// Whether the fire button was down last frame, only new clicks fire.
+    was_firing: bool,
+}
+
+impl Gun {
+    pub fn new() -> Self {
+        Self {
+            energy: GUN_MAX_ENERGY,
+            // Don't fire with the click that started the level
+            was_firing: true,
+        }
+    }
+
+    /// Draw the energy meter.
+    pub fn render(&self, buffer: &mut PixelBuffer, palette: &Palette, x: usize, y: usize) {
+        let color = if self.energy >= GUN_SHOT_ENERGY {
+            palette.friendly_projectile
+        } else {
+            palette.ui_inactive
+        };
+
+        gui::draw_meter(
+            buffer,
+            palette,
+            (x, y),
+            (GUN_METER_WIDTH, GUN_METER_HEIGHT),
+            self.energy / GUN_MAX_ENERGY,
+            color,
+        );
+    }
+}
+
+/// Built up by holding the button, the next reflection uses it to speed up the projectile.
 #[derive(Component, Debug, Default)]
-#[storage(NullStorage)]
-pub struct Player;
+pub struct Charge(f64);
+
+impl Charge {
+    /// Use the charge for a reflection, returns the speed multiplier and the amount of enemies the
+    /// projectile can destroy.
+    pub fn release(&mut self) -> Option<(f64, u8)> {
+        let charge = std::mem::take(&mut self.0);
+        if charge < CHARGE_MIN {
+            return None;
+        }
+
+        Some((
+            1.0 + charge * CHARGE_SPEED_BONUS,
+            1 + (charge * CHARGE_DAMAGE_BONUS).round() as u8,
+        ))
+    }
+
+    /// Draw the charge meter at the left side of the paddle.
+    pub fn render(
+        &self,
+        buffer: &mut PixelBuffer,
+        palette: &Palette,
+        pos: &Position,
+        bb: &BoundingBox,
+    ) {
+        let height = (self.0 * bb.y) as usize;
+        if height == 0 {
+            return;
+        }
+
+        let (x, bottom) = (
+            pos.x as usize - CHARGE_METER_WIDTH - 2,
+            (pos.y + bb.y) as usize,
+        );
+        let color = if self.0 >= CHARGE_MIN {
+            palette.friendly_projectile
+        } else {
+            palette.ui_inactive
+        };
+
+        let width = buffer.width();
+        let pixels = buffer.pixels_mut();
+        for meter_y in bottom.saturating_sub(height)..bottom.min(crate::HEIGHT) {
+            for meter_x in x..x + CHARGE_METER_WIDTH {
+                pixels[meter_y * width + meter_x] = color;
+            }
+        }
+    }
+}
+
+/// System that charges the paddles while their button is held.
+///
+/// With the hold upgrade the button catches projectiles instead of reflecting them charged, so
+/// the paddle only charges while it holds a projectile and the charge is used when it's released.
+pub struct ChargeSystem;
+impl<'a> System<'a> for ChargeSystem {
+    type SystemData = (
+        Read<'a, Input>,
+        Read<'a, RemoteInput>,
+        Read<'a, Upgrades>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, HoldProjectile>,
+        WriteStorage<'a, Charge>,
+    );
+
+    fn run(&mut self, (input, remote, upgrades, player, hold, mut charge): Self::SystemData) {
+        for (player, charge) in (&player, &mut charge).join() {
+            let usable = !upgrades.hold || hold.join().any(|hold| hold.player == player.id);
+
+            charge.0 = if usable && player.controller.holding(&input, &remote) {
+                (charge.0 + 1.0 / CHARGE_TIME).min(1.0)
+            } else {
+                (charge.0 - 1.0 / CHARGE_DECAY_TIME).max(0.0)
+            };
+        }
+    }
+}
+
+/// System that fires the guns of the paddles.
+pub struct GunSystem;
+impl<'a> System<'a> for GunSystem {
+    type SystemData = (
+        Read<'a, Input>,
+        Read<'a, RemoteInput>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Gun>,
+        WriteStorage<'a, ProjectileEmitter>,
+    );
+
+    fn run(&mut self, (input, remote, player, mut gun, mut emitter): Self::SystemData) {
+        for (player, gun, emitter) in (&player, &mut gun, &mut emitter).join() {
+            // The shot requested last frame got fired
+            if emitter.fired() {
+                gun.energy -= GUN_SHOT_ENERGY;
+            }
+            gun.energy = (gun.energy + GUN_RECHARGE).min(GUN_MAX_ENERGY);
+
+            let firing = player.controller.firing(&input, &remote);
+            if firing && !gun.was_firing && gun.energy >= GUN_SHOT_ENERGY {
+                emitter.trigger();
+            }
+            gun.was_firing = firing;
+        }
+    }
+}
 
 /// System processes the player input.
 pub struct PlayerSystem;
 impl<'a> System<'a> for PlayerSystem {
     type SystemData = (
         Read<'a, Input>,
+        Read<'a, RemoteInput>,
         ReadStorage<'a, Player>,
         WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Speed>,
         ReadStorage<'a, BoundingBox>,
     );
 
-    fn run(&mut self, (input, player, mut pos, bb): Self::SystemData) {
-        for (pos, bb, _) in (&mut pos, &bb, &player).join() {
-            let offset = bb.center_offset();
-            pos.y = input.mouse_y() as f64 - offset.y;
+    fn run(&mut self, (input, remote, player, mut pos, mut vel, speed, bb): Self::SystemData) {
+        for (pos, vel, speed, bb, player) in (&mut pos, &mut vel, &speed, &bb, &player).join() {
+            match player.controller {
+                Controller::Mouse => {
+                    let offset = bb.center_offset();
+                    pos.y = input.mouse_y() as f64 - offset.y;
+                }
+                Controller::Remote => {
+                    let offset = bb.center_offset();
+                    pos.y = remote.mouse_y() as f64 - offset.y;
+                }
+                Controller::Keyboard => {
+                    vel.y += Controller::keyboard_direction(&input) * speed.0;
+                }
+                Controller::Bot => (),
+            }
         }
     }
 }
 
 /// Spawn a new player.
-pub fn spawn_player(world: &mut World) -> Result<()> {
-    let (width, height, options) = (
-        11,
-        22,
-        Options {
-            mirror_x: false,
-            mirror_y: true,
-            colored: true,
-            edge_brightness: 0.33511457,
-            color_variations: 0.01,
-            brightness_noise: 0.50169325,
-            saturation: 0.4671184,
-            seed: quad_rand::rand() as u64,
-        },
-    );
-    let data = [
-        Empty, Empty, Empty, Empty, Empty, Empty, Empty, Empty, Empty, Empty, Empty, Body1, Body1,
-        Body1, Body1, Body1, Body1, Body1, Body1, Body1, Solid, Empty, Body2, Body2, Body2, Body2,
-        Body2, Empty, Empty, Empty, Body1, Solid, Empty, Body2, Body2, Body2, Empty, Body2, Body2,
-        Body2, Body2, Body1, Empty, Empty, Empty, Body1, Body1, Empty, Empty, Empty, Body2, Empty,
-        Body1, Empty, Empty, Empty, Body1, Empty, Empty, Solid, Solid, Body2, Empty, Body1, Solid,
-        Empty, Empty, Empty, Empty, Empty, Empty, Empty, Body2, Empty, Body1, Solid, Empty, Empty,
-        Solid, Solid, Empty, Empty, Solid, Body2, Body2, Body1, Empty, Empty, Empty, Body1, Body1,
-        Empty, Empty, Body1, Body2, Body2, Body1, Empty, Empty, Empty, Body1, Empty, Body1, Empty,
-        Body1, Empty, Body2, Body1, Solid, Empty, Empty, Empty, Body1, Empty, Empty, Body1, Body1,
-        Body2, Body1, Solid, Empty, Empty, Empty, Body1, Empty, Empty, Body1, Body1, Body2, Body1,
-        Empty, Empty, Empty, Empty, Body1, Empty, Empty, Empty, Empty, Empty, Body1, Empty, Empty,
-        Empty, Body2, Body2, Body2, Empty, Body1, Body2, Empty, Body1, Solid, Empty, Empty, Empty,
-        Body2, Empty, Empty, Body1, Empty, Empty, Body1, Solid, Empty, Empty, Empty, Body2, Empty,
-        Empty, Empty, Body1, Empty, Body1, Empty, Empty, Empty, Empty, Body2, Empty, Body1, Empty,
-        Empty, Empty, Body1, Empty, Empty, Empty, Empty, Body2, Empty, Body1, Empty, Empty, Empty,
-        Body1, Solid, Empty, Empty, Empty, Body2, Empty, Body1, Empty, Empty, Body2, Body1, Solid,
-        Empty, Empty, Empty, Body2, Empty, Body1, Body1, Empty, Body2, Body1, Empty, Empty, Empty,
-        Empty, Body2, Empty, Body1, Body1, Empty, Body2, Body1, Empty, Empty, Empty, Body2, Body2,
-        Body2, Body1, Body1, Empty, Empty, Body1, Solid, Empty,
-    ];
-    let sprite = sprite::generate(width, options, &data)?;
-
-    world
+pub fn spawn_player(world: &mut World, id: u8, controller: Controller) -> Result<()> {
+    let (sprite, width, height) = world.read_resource::<Sprites>().paddle();
+
+    let paddle = world
         .create_entity()
-        .with(Sprite::new(sprite))
-        .with(Player)
-        .with(Position::new(10.0, 200.0))
+        .with(sprite.sprite())
+        .with(sprite.id())
+        .with(Player::new(id, controller))
+        .with(Position::new(
+            10.0 + id as f64 * PLAYER_SPACING,
+            200.0 - id as f64 * 100.0,
+        ))
         .with(Velocity::new(0.0, 0.0))
         .with(Drag(PLAYER_DRAG))
-        .with(Speed(PLAYER_SPEED))
-        .with(BoundingBox::new(width as f64, height as f64 * 2.0))
+        .with(Speed(match controller {
+            Controller::Keyboard | Controller::Bot => KEYBOARD_SPEED,
+            Controller::Mouse | Controller::Remote => PLAYER_SPEED,
+        }))
+        .with(Charge::default())
+        .with(BoundingBox::new(width, height))
         .build();
 
+    if world.read_resource::<Upgrades>().dash {
+        world
+            .write_storage::<Dash>()
+            .insert(paddle, Dash::default())?;
+    }
+    if world.read_resource::<Upgrades>().barrier {
+        world
+            .write_storage::<Barrier>()
+            .insert(paddle, Barrier::default())?;
+    }
+
+    if world.read_resource::<Upgrades>().gun {
+        let shot = world.read_resource::<Sprites>().shot.clone();
+        let emitter = ProjectileEmitter::new(
+            shot,
+            BoundingBox::new(SHOT_WIDTH as f64, SHOT_HEIGHT as f64),
+        )
+        .with_owner(id)
+        .with_pattern(Pattern::Forward)
+        .with_interval(GUN_COOLDOWN)
+        .with_speed(GUN_SPEED)
+        // Fire from the front of the paddle
+        .with_offset(vek::Vec2::new(width, (height - SHOT_HEIGHT as f64) / 2.0));
+
+        world.write_storage::<Gun>().insert(paddle, Gun::new())?;
+        world
+            .write_storage::<ProjectileEmitter>()
+            .insert(paddle, emitter)?;
+    }
+
     Ok(())
 }
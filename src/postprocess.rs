@@ -0,0 +1,323 @@
+use miniquad::KeyCode;
+use specs_blit::PixelBuffer;
+
+/// Brightness of the darkened rows.
+const SCANLINE_BRIGHTNESS: u32 = 192;
+/// How much the corners of the screen bend inwards.
+const CURVATURE: f64 = 0.04;
+/// Pixels with a summed brightness above this glow.
+const BLOOM_THRESHOLD: u32 = 0xFF * 2;
+/// Pixels the glow spreads in every direction.
+const BLOOM_RADIUS: usize = 3;
+/// Brightness of the glow, out of 256.
+const BLOOM_STRENGTH: u32 = 160;
+/// Pixels the red and blue channels are moved apart.
+const ABERRATION_OFFSET: usize = 1;
+/// How dark the corners become, out of 1.
+const VIGNETTE_STRENGTH: f64 = 0.6;
+
+/// Effects applied to the pixel buffer before it's shown, each can be toggled with a function key.
+pub struct PostProcess {
+    /// Darken every other row, `F1`.
+    pub scanlines: bool,
+    /// Bend the screen like an old monitor, `F2`.
+    pub curvature: bool,
+    /// Let bright pixels glow, `F3`.
+    pub bloom: bool,
+    /// Move the red and blue channels apart, `F4`.
+    pub chromatic_aberration: bool,
+    /// Darken the corners, `F5`.
+    pub vignette: bool,
+
+    width: usize,
+    height: usize,
+    /// For every pixel the pixel it takes its color from when the screen is curved.
+    curvature_map: Vec<Option<usize>>,
+    /// For every pixel the brightness out of 256 when the vignette is applied.
+    vignette_map: Vec<u32>,
+    /// Copy of the pixels for the effects that move them around.
+    scratch: Vec<u32>,
+    /// The summed red, green and blue of the glow.
+    glow: Vec<[u32; 3]>,
+    glow_scratch: Vec<[u32; 3]>,
+}
+
+impl PostProcess {
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = width * height;
+
+        let (center_x, center_y) = (width as f64 / 2.0, height as f64 / 2.0);
+        let normalized = |index: usize| {
+            (
+                (index % width) as f64 / center_x - 1.0,
+                (index / width) as f64 / center_y - 1.0,
+            )
+        };
+
+        let curvature_map = (0..size)
+            .map(|index| {
+                let (x, y) = normalized(index);
+                // Barrel distortion, the further from the center the more it's stretched
+                let (x, y) = (x * (1.0 + CURVATURE * y * y), y * (1.0 + CURVATURE * x * x));
+                let (x, y) = ((x + 1.0) * center_x, (y + 1.0) * center_y);
+
+                if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+                    None
+                } else {
+                    Some(y as usize * width + x as usize)
+                }
+            })
+            .collect();
+
+        let vignette_map = (0..size)
+            .map(|index| {
+                let (x, y) = normalized(index);
+                let distance = (x * x + y * y) / 2.0;
+
+                ((1.0 - distance * distance * VIGNETTE_STRENGTH) * 256.0) as u32
+            })
+            .collect();
+
+        Self {
+            scanlines: false,
+            curvature: false,
+            bloom: true,
+            chromatic_aberration: false,
+            vignette: true,
+            width,
+            height,
+            curvature_map,
+            vignette_map,
+            scratch: vec![0; size],
+            glow: vec![[0; 3]; size],
+            glow_scratch: vec![[0; 3]; size],
+        }
+    }
+
+    /// Toggle the effect belonging to the function key, returns whether the key was used.
+    pub fn toggle(&mut self, keycode: KeyCode) -> bool {
+        let effect = match keycode {
+            KeyCode::F1 => &mut self.scanlines,
+            KeyCode::F2 => &mut self.curvature,
+            KeyCode::F3 => &mut self.bloom,
+            KeyCode::F4 => &mut self.chromatic_aberration,
+            KeyCode::F5 => &mut self.vignette,
+            _ => return false,
+        };
+        *effect = !*effect;
+
+        true
+    }
+
+    /// Apply all enabled effects to the buffer.
+    pub fn apply(&mut self, buffer: &mut PixelBuffer) {
+        let pixels = buffer.pixels_mut();
+
+        if self.bloom {
+            self.apply_bloom(pixels);
+        }
+        if self.chromatic_aberration {
+            self.apply_chromatic_aberration(pixels);
+        }
+        if self.curvature {
+            self.apply_curvature(pixels);
+        }
+        if self.scanlines {
+            self.apply_scanlines(pixels);
+        }
+        if self.vignette {
+            for (pixel, brightness) in pixels.iter_mut().zip(self.vignette_map.iter()) {
+                *pixel = scale(*pixel, *brightness);
+            }
+        }
+    }
+
+    fn apply_scanlines(&self, pixels: &mut [u32]) {
+        for row in pixels.chunks_exact_mut(self.width).skip(1).step_by(2) {
+            for pixel in row {
+                *pixel = scale(*pixel, SCANLINE_BRIGHTNESS);
+            }
+        }
+    }
+
+    fn apply_curvature(&mut self, pixels: &mut [u32]) {
+        self.scratch.copy_from_slice(pixels);
+
+        for (pixel, source) in pixels.iter_mut().zip(self.curvature_map.iter()) {
+            *pixel = source.map_or(0xFF000000, |source| self.scratch[source]);
+        }
+    }
+
+    fn apply_bloom(&mut self, pixels: &mut [u32]) {
+        // Only keep the bright pixels
+        for (glow, pixel) in self.glow.iter_mut().zip(pixels.iter()) {
+            let rgb = channels(*pixel);
+            *glow = if rgb.iter().sum::<u32>() > BLOOM_THRESHOLD {
+                rgb
+            } else {
+                [0; 3]
+            };
+        }
+
+        // Spread them with a horizontal and a vertical box blur
+        blur(
+            &self.glow,
+            &mut self.glow_scratch,
+            self.width,
+            self.height,
+            1,
+            self.width,
+        );
+        blur(
+            &self.glow_scratch,
+            &mut self.glow,
+            self.height,
+            self.width,
+            self.width,
+            1,
+        );
+
+        let divider = (BLOOM_RADIUS * 2 + 1).pow(2) as u32;
+        for (pixel, glow) in pixels.iter_mut().zip(self.glow.iter()) {
+            let [r, g, b] = channels(*pixel);
+            let add = |channel: u32, glow: u32| {
+                (channel + glow * BLOOM_STRENGTH / 256 / divider).min(0xFF)
+            };
+
+            *pixel = color(add(r, glow[0]), add(g, glow[1]), add(b, glow[2]));
+        }
+    }
+
+    fn apply_chromatic_aberration(&mut self, pixels: &mut [u32]) {
+        self.scratch.copy_from_slice(pixels);
+
+        for (row, source) in pixels
+            .chunks_exact_mut(self.width)
+            .zip(self.scratch.chunks_exact(self.width))
+        {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let [_, g, _] = channels(source[x]);
+                let [r, _, _] = channels(source[x.saturating_sub(ABERRATION_OFFSET)]);
+                let [_, _, b] = channels(source[(x + ABERRATION_OFFSET).min(self.width - 1)]);
+
+                *pixel = color(r, g, b);
+            }
+        }
+    }
+}
+
+/// Sum every pixel with its neighbors along lines, `step` is the distance between two pixels in
+/// a line and `stride` the distance between two lines.
+fn blur(
+    source: &[[u32; 3]],
+    target: &mut [[u32; 3]],
+    length: usize,
+    lines: usize,
+    step: usize,
+    stride: usize,
+) {
+    for line in 0..lines {
+        let index = |position: usize| line * stride + position * step;
+
+        let mut sum = [0; 3];
+        for position in 0..BLOOM_RADIUS.min(length) {
+            add(&mut sum, source[index(position)]);
+        }
+
+        for position in 0..length {
+            // Slide the window one pixel further
+            if position + BLOOM_RADIUS < length {
+                add(&mut sum, source[index(position + BLOOM_RADIUS)]);
+            }
+            if position > BLOOM_RADIUS {
+                let removed = source[index(position - BLOOM_RADIUS - 1)];
+                (0..3).for_each(|channel| sum[channel] -= removed[channel]);
+            }
+
+            target[index(position)] = sum;
+        }
+    }
+}
+
+fn add(sum: &mut [u32; 3], value: [u32; 3]) {
+    (0..3).for_each(|channel| sum[channel] += value[channel]);
+}
+
+/// The red, green and blue channels of a pixel.
+fn channels(pixel: u32) -> [u32; 3] {
+    [pixel & 0xFF, (pixel >> 8) & 0xFF, (pixel >> 16) & 0xFF]
+}
+
+/// Combine the channels into an opaque pixel.
+fn color(r: u32, g: u32, b: u32) -> u32 {
+    r | (g << 8) | (b << 16) | 0xFF000000
+}
+
+/// Multiply every channel with a brightness out of 256.
+fn scale(pixel: u32, brightness: u32) -> u32 {
+    let [r, g, b] = channels(pixel);
+
+    color(
+        r * brightness / 256,
+        g * brightness / 256,
+        b * brightness / 256,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: u32 = 0xFFFFFFFF;
+    const BLACK: u32 = 0xFF000000;
+
+    /// Fill a buffer with a single color and apply only the effects that are turned on.
+    fn process(
+        width: usize,
+        height: usize,
+        pixel: u32,
+        enable: impl FnOnce(&mut PostProcess),
+    ) -> PixelBuffer {
+        let mut post_process = PostProcess::new(width, height);
+        post_process.bloom = false;
+        post_process.vignette = false;
+        enable(&mut post_process);
+
+        let mut buffer = PixelBuffer::new(width, height);
+        buffer.pixels_mut().iter_mut().for_each(|p| *p = pixel);
+        post_process.apply(&mut buffer);
+
+        buffer
+    }
+
+    #[test]
+    fn scanlines_darken_odd_rows() {
+        let buffer = process(4, 4, WHITE, |p| p.scanlines = true);
+
+        for (y, row) in buffer.pixels().chunks_exact(4).enumerate() {
+            let expected = if y % 2 == 0 { WHITE } else { 0xFFBFBFBF };
+            assert!(row.iter().all(|pixel| *pixel == expected), "row {}", y);
+        }
+    }
+
+    #[test]
+    fn vignette_keeps_center() {
+        let pixel = 0xFF336699;
+        let buffer = process(8, 6, pixel, |p| p.vignette = true);
+
+        assert_eq!(buffer.pixels()[3 * 8 + 4], pixel);
+        assert_ne!(buffer.pixels()[0], pixel);
+    }
+
+    #[test]
+    fn curvature_leaves_corners_black() {
+        let (width, height) = (400, 300);
+        let buffer = process(width, height, WHITE, |p| p.curvature = true);
+        let pixels = buffer.pixels();
+
+        for corner in [0, width - 1, (height - 1) * width, height * width - 1] {
+            assert_eq!(pixels[corner], BLACK, "corner {}", corner);
+        }
+        assert_eq!(pixels[height / 2 * width + width / 2], WHITE);
+    }
+}
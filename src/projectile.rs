@@ -1,17 +1,24 @@
 use crate::{
-    color,
-    effect::ScreenFlash,
+    ability::{Barrier, Dash},
+    color::Palette,
+    effect::{Camera, ScreenFlash, LIFE_LOST_SHAKE},
+    enemy::Enemy,
     entity::Lifetime,
-    input::Input,
+    input::{Input, RemoteInput},
     lives::Lives,
     particle::ParticleEmitter,
+    pattern::{Mine, Pattern},
     physics::*,
-    player::Player,
+    player::{Charge, Player},
     random,
-    sprite::Sprites,
+    script::Script,
+    sprite::{RotationFollowsVelocity, SpriteRef, Sprites, LASER_HEIGHT, LASER_LENGTH},
     upgrade::{HoldProjectile, Upgrades},
+    versus::Commander,
 };
-use specs_blit::{specs::*, Sprite, SpriteRef};
+use derive_deref::{Deref, DerefMut};
+use specs_blit::specs::*;
+use std::f64::consts::PI;
 
 type Vec2 = vek::Vec2<f64>;
 
@@ -23,6 +30,69 @@ pub struct Projectile;
 #[derive(Component, Debug)]
 pub struct SplitInto(SpriteRef);
 
+/// The amount of enemies a projectile can destroy before it's gone, a single one when missing.
+#[derive(Component, Debug, Deref, DerefMut, Clone)]
+pub struct Damage(pub u8);
+
+/// The player that last reflected the projectile.
+#[derive(Component, Debug, Deref, DerefMut, Clone)]
+pub struct ReflectedBy(pub u8);
+
+/// How fast a missile can turn, between 0 and 1.
+const MISSILE_TURN: f64 = 0.05;
+
+/// A projectile steering towards the closest paddle, or the closest enemy after it's reflected.
+#[derive(Component, Debug)]
+pub struct Missile {
+    fuel: f64,
+    max_fuel: f64,
+}
+
+impl Missile {
+    pub fn new(fuel: f64) -> Self {
+        Self {
+            fuel,
+            max_fuel: fuel,
+        }
+    }
+
+    /// Give it a full tank again after it got reflected.
+    pub fn refuel(&mut self) {
+        self.fuel = self.max_fuel;
+    }
+}
+
+/// A beam fired by an enemy that can't be reflected, a paddle hit by it loses a life.
+#[derive(Component, Debug)]
+pub struct Laser {
+    /// The enemy firing it, the beam stops when it dies.
+    emitter: Entity,
+    /// Frames left before the beam fires.
+    warmup: f64,
+    /// Frames left while firing.
+    duration: f64,
+    sweep: Vec2,
+    /// Every beam can only cost a single life.
+    hit: bool,
+}
+
+impl Laser {
+    pub fn new(emitter: Entity, warmup: f64, duration: f64, sweep: Vec2) -> Self {
+        Self {
+            emitter,
+            warmup,
+            duration,
+            sweep,
+            hit: false,
+        }
+    }
+
+    /// Whether the beam is firing instead of being shown as a warning.
+    pub fn firing(&self) -> bool {
+        self.warmup <= 0.0
+    }
+}
+
 /// A component that emits projectiles while it lives.
 #[derive(Component, Debug)]
 pub struct ProjectileEmitter {
@@ -37,6 +107,17 @@ pub struct ProjectileEmitter {
     /// Optional offset.
     offset: Vec2,
     size: BoundingBox,
+    /// Fire this frame if reloaded, only used when a commander controls the enemies.
+    triggered: bool,
+    /// Whether a projectile was fired this frame.
+    fired: bool,
+    pattern: Pattern,
+    /// Shots left in the current burst.
+    burst_left: usize,
+    burst_timer: f64,
+    spiral_angle: f64,
+    /// The player firing the projectiles, their emitters only fire when triggered.
+    owner: Option<u8>,
 }
 
 impl ProjectileEmitter {
@@ -51,6 +132,13 @@ impl ProjectileEmitter {
             current_interval: random::range(0.0, interval),
             offset: Vec2::new(0.0, 0.0),
             size,
+            triggered: false,
+            fired: false,
+            pattern: Pattern::default(),
+            burst_left: 0,
+            burst_timer: 0.0,
+            spiral_angle: PI,
+            owner: None,
         }
     }
 
@@ -84,6 +172,28 @@ impl ProjectileEmitter {
 
         self
     }
+
+    pub fn with_pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = pattern;
+
+        self
+    }
+
+    pub fn with_owner(mut self, player: u8) -> Self {
+        self.owner = Some(player);
+
+        self
+    }
+
+    /// Request a shot, it's ignored when the emitter is still reloading.
+    pub fn trigger(&mut self) {
+        self.triggered = true;
+    }
+
+    /// Whether a projectile was fired this frame.
+    pub fn fired(&self) -> bool {
+        self.fired
+    }
 }
 
 /// System that will spawn projectiles.
@@ -92,44 +202,252 @@ impl<'a> System<'a> for ProjectileEmitterSystem {
     type SystemData = (
         Entities<'a>,
         ReadExpect<'a, Sprites>,
+        Read<'a, Palette>,
+        Option<Read<'a, Commander>>,
+        ReadStorage<'a, Script>,
         WriteStorage<'a, ProjectileEmitter>,
+        ReadStorage<'a, Player>,
         ReadStorage<'a, Position>,
+        ReadStorage<'a, BoundingBox>,
         Read<'a, LazyUpdate>,
     );
 
-    fn run(&mut self, (entities, sprites, mut emitter, pos, updater): Self::SystemData) {
-        for (emitter, pos) in (&mut emitter, &pos).join() {
+    fn run(
+        &mut self,
+        (
+            entities,
+            sprites,
+            palette,
+            commander,
+            script,
+            mut emitter,
+            player,
+            pos,
+            bb,
+            updater,
+        ): Self::SystemData,
+    ) {
+        let paddles = (&player, &pos, &bb)
+            .join()
+            .map(|(_, pos, bb)| pos.0 + bb.center_offset())
+            .collect::<Vec<_>>();
+
+        for (entity, emitter, pos, script) in
+            (&*entities, &mut emitter, &pos, (&script).maybe()).join()
+        {
             emitter.current_interval += 1.0;
-            if emitter.current_interval > emitter.interval && pos.x > 200.0 {
+
+            let reloaded = emitter.current_interval > emitter.interval;
+            // When the enemies are commanded by a player or a script they only shoot on request, as
+            // do the paddles
+            let fire = if commander.is_some() || script.is_some() || emitter.owner.is_some() {
+                emitter.triggered && reloaded
+            } else {
+                reloaded
+            };
+            emitter.triggered = false;
+            emitter.fired = fire && (emitter.owner.is_some() || pos.x > 200.0);
+
+            if emitter.fired {
                 emitter.current_interval = 0.0;
 
+                if let Pattern::Burst { shots, .. } = emitter.pattern {
+                    emitter.burst_left = shots;
+                    emitter.burst_timer = 0.0;
+                }
+            }
+
+            // Keep firing the rest of the burst
+            let mut volley = emitter.fired;
+            if let Pattern::Burst { delay, .. } = emitter.pattern {
+                volley = false;
+                if emitter.burst_left > 0 {
+                    emitter.burst_timer -= 1.0;
+                    if emitter.burst_timer <= 0.0 {
+                        emitter.burst_timer = delay;
+                        emitter.burst_left -= 1;
+                        volley = true;
+                    }
+                }
+            }
+            if !volley {
+                continue;
+            }
+
+            let origin = pos.add_offset(emitter.offset);
+            let center = origin.0 + emitter.size.center_offset();
+            let paddle = paddles
+                .iter()
+                .min_by(|a, b| a.distance(center).total_cmp(&b.distance(center)))
+                .copied();
+
+            let velocities = emitter.pattern.volley(
+                emitter.speed,
+                emitter.spread,
+                center,
+                paddle,
+                &mut emitter.spiral_angle,
+            );
+            for vel in velocities {
+                if let Pattern::Laser {
+                    warmup, duration, ..
+                } = emitter.pattern
+                {
+                    // The beam ends at the enemy and crosses the rest of the screen
+                    let laser = entities.create();
+                    updater.insert(laser, Laser::new(entity, warmup, duration, vel));
+                    updater.insert(
+                        laser,
+                        Position::new(
+                            center.x - LASER_LENGTH as f64,
+                            center.y - LASER_HEIGHT as f64 / 2.0,
+                        ),
+                    );
+                    updater.insert(
+                        laser,
+                        BoundingBox::new(LASER_LENGTH as f64, LASER_HEIGHT as f64),
+                    );
+                    updater.insert(laser, sprites.laser_warning.sprite());
+                    updater.insert(laser, sprites.laser_warning.id());
+
+                    continue;
+                }
+
+                let (sprite, size) = match emitter.pattern {
+                    Pattern::Missile { .. } => {
+                        let (sprite, width, height) = sprites.missile();
+
+                        (sprite, BoundingBox::new(width, height))
+                    }
+                    _ => (emitter.sprite.clone(), emitter.size.clone()),
+                };
+
                 // Spawn a new projectile
                 let projectile = entities.create();
                 updater.insert(projectile, Projectile);
                 // Clone the position of the emitter
-                updater.insert(projectile, pos.add_offset(emitter.offset));
-                // Add a new random velocity
-                updater.insert(
-                    projectile,
-                    Velocity::new(
-                        -emitter.speed,
-                        random::range(-emitter.spread, emitter.spread),
-                    ),
-                );
-                updater.insert(projectile, emitter.size.clone());
+                updater.insert(projectile, origin.clone());
+                updater.insert(projectile, Velocity::from_vec2(vel));
+                updater.insert(projectile, trail(&size, false, &palette));
+                updater.insert(projectile, size);
                 // Use the sprite reference of the emitter
-                updater.insert(projectile, Sprite::new(emitter.sprite.clone()));
+                updater.insert(projectile, sprite.sprite());
+                updater.insert(projectile, sprite.id());
 
                 if let Some(ref sprite) = emitter.split_into {
                     updater.insert(projectile, SplitInto(sprite.clone()));
                 }
 
-                updater.insert(
-                    projectile,
-                    ParticleEmitter::new(3.0, sprites.white_particle.clone())
-                        .with_dispersion(1.0)
-                        .with_offset(emitter.size.center_offset()),
-                );
+                if let Some(owner) = emitter.owner {
+                    // Count the hits for the score of the player
+                    updater.insert(projectile, ReflectedBy(owner));
+                    // Remove it when it's flying off the screen
+                    updater.insert(
+                        projectile,
+                        Lifetime::new(crate::WIDTH as f64 / emitter.speed),
+                    );
+                }
+
+                match emitter.pattern {
+                    Pattern::Mine { delay } => {
+                        updater.insert(projectile, Mine::new(delay, emitter.speed))
+                    }
+                    Pattern::Missile { fuel } => {
+                        updater.insert(projectile, Missile::new(fuel));
+                        updater.insert(projectile, RotationFollowsVelocity);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// System that steers the missiles.
+pub struct MissileSystem;
+impl<'a> System<'a> for MissileSystem {
+    type SystemData = (
+        WriteStorage<'a, Missile>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, ReflectedBy>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, BoundingBox>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(
+        &mut self,
+        (mut missile, player, enemy, reflected_by, pos, bb, mut vel): Self::SystemData,
+    ) {
+        let paddles = (&player, &pos, &bb)
+            .join()
+            .map(|(_, pos, bb)| pos.0 + bb.center_offset())
+            .collect::<Vec<_>>();
+        let enemies = (&enemy, &pos, &bb)
+            .join()
+            .map(|(_, pos, bb)| pos.0 + bb.center_offset())
+            .collect::<Vec<_>>();
+
+        for (missile, pos, bb, vel, reflected_by) in
+            (&mut missile, &pos, &bb, &mut vel, (&reflected_by).maybe()).join()
+        {
+            // Keep flying straight when the fuel is gone
+            if missile.fuel <= 0.0 {
+                continue;
+            }
+            missile.fuel -= 1.0;
+
+            let center = pos.0 + bb.center_offset();
+            let targets = if reflected_by.is_some() {
+                &enemies
+            } else {
+                &paddles
+            };
+            let target = targets
+                .iter()
+                .min_by(|a, b| a.distance(center).total_cmp(&b.distance(center)));
+
+            if let Some(target) = target {
+                let speed = vel.magnitude();
+                let desired = (*target - center).normalized() * speed;
+                vel.0 = Vec2::lerp(vel.0, desired, MISSILE_TURN).normalized() * speed;
+            }
+        }
+    }
+}
+
+/// System that fires the lasers after their warning and removes them when they are done.
+pub struct LaserSystem;
+impl<'a> System<'a> for LaserSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Sprites>,
+        WriteStorage<'a, Laser>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, sprites, mut laser, updater): Self::SystemData) {
+        for (entity, laser) in (&*entities, &mut laser).join() {
+            if !entities.is_alive(laser.emitter) {
+                let _ = entities.delete(entity);
+                continue;
+            }
+
+            if laser.firing() {
+                laser.duration -= 1.0;
+                if laser.duration <= 0.0 {
+                    let _ = entities.delete(entity);
+                }
+                continue;
+            }
+
+            laser.warmup -= 1.0;
+            if laser.firing() {
+                // Replace the warning with the beam and start sweeping
+                updater.insert(entity, sprites.laser.sprite());
+                updater.insert(entity, sprites.laser.id());
+                updater.insert(entity, Velocity::from_vec2(laser.sweep));
             }
         }
     }
@@ -140,15 +458,22 @@ impl<'a> System<'a> for ProjectileSystem {
     type SystemData = (
         Entities<'a>,
         Option<Write<'a, Lives>>,
-        ReadExpect<'a, Sprites>,
+        Write<'a, Camera>,
+        Read<'a, Palette>,
         Read<'a, Upgrades>,
         Read<'a, Input>,
+        Read<'a, RemoteInput>,
         ReadStorage<'a, Projectile>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, BoundingBox>,
         ReadStorage<'a, SplitInto>,
         ReadStorage<'a, HoldProjectile>,
+        WriteStorage<'a, Missile>,
+        WriteStorage<'a, Laser>,
+        WriteStorage<'a, Charge>,
+        ReadStorage<'a, Dash>,
+        ReadStorage<'a, Barrier>,
         WriteStorage<'a, Velocity>,
         Read<'a, LazyUpdate>,
     );
@@ -158,51 +483,118 @@ impl<'a> System<'a> for ProjectileSystem {
         (
             entities,
             lives,
-            sprites,
+            mut camera,
+            palette,
             upgrades,
             input,
+            remote,
             projectile,
             mut pos,
             player,
             bb,
             split_into,
             hold,
+            mut missile,
+            mut laser,
+            mut charge,
+            dash,
+            barrier,
             mut vel,
             updater,
         ): Self::SystemData,
     ) {
         if upgrades.hold {
-            let mut player_pos = Vec2::zero();
-            for (pos, _) in (&pos, &player).join() {
-                player_pos = pos.0;
-            }
-
-            for (entity, mut projectile_pos, projectile_hold, _) in
+            let players = (&pos, &player)
+                .join()
+                .map(|(pos, player)| {
+                    (
+                        player.id(),
+                        pos.0,
+                        player.controller().holding(&input, &remote),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            for (entity, projectile_pos, projectile_hold, _) in
                 (&*entities, &mut pos, &hold, &projectile).join()
             {
-                projectile_pos.0 = player_pos + projectile_hold.0;
-                if !input.mouse_down() {
-                    // When the mouse is released release all entities
-                    updater.remove::<HoldProjectile>(entity);
+                match players
+                    .iter()
+                    .find(|(id, _, _)| *id == projectile_hold.player)
+                {
+                    Some((_, player_pos, holding)) => {
+                        projectile_pos.0 = *player_pos + projectile_hold.offset;
+                        if !holding {
+                            // When the button is released release all entities
+                            updater.remove::<HoldProjectile>(entity);
+                        }
+                    }
+                    None => updater.remove::<HoldProjectile>(entity),
                 }
             }
         }
 
         if let Some(mut lives) = lives {
-            for (entity, pos, _) in (&*entities, &pos, &projectile).join() {
+            let shielded = barrier.join().any(Barrier::active);
+            for (entity, pos, projectile_bb, _) in (&*entities, &pos, &bb, &projectile).join() {
                 if pos.0.x <= 0.0 {
-                    lives.reduce();
+                    if shielded {
+                        // The barrier absorbs it
+                        let emitter = entities.create();
+                        updater.insert(
+                            emitter,
+                            ParticleEmitter::new(15.0, palette.heal)
+                                .with_burst(12)
+                                .with_fade()
+                                .with_dispersion(2.0)
+                                .with_drag(0.85),
+                        );
+                        updater.insert(emitter, pos.clone());
+                        updater.insert(emitter, Lifetime::new(2.0));
+                    } else {
+                        lives.hit(pos.y + projectile_bb.y / 2.0);
+                        camera.shake(LIFE_LOST_SHAKE);
+
+                        let flash = entities.create();
+                        updater.insert(flash, ScreenFlash::new(palette.danger));
+                        updater.insert(flash, Lifetime::new(5.0));
+                    }
+
+                    let _ = entities.delete(entity);
+                }
+            }
+
+            // Lasers can't be reflected so the paddles have to get out of the way, unless they
+            // just dashed
+            let paddles = (&pos, &bb, &player, (&dash).maybe())
+                .join()
+                .filter(|(_, _, _, dash)| !dash.is_some_and(Dash::invulnerable))
+                .map(|(pos, bb, _, _)| bb.to_aabr(pos))
+                .collect::<Vec<_>>();
+            for (laser_pos, laser_bb, laser) in (&pos, &bb, &mut laser).join() {
+                if !laser.firing() || laser.hit {
+                    continue;
+                }
+
+                let laser_aabr = laser_bb.to_aabr(laser_pos);
+                if paddles
+                    .iter()
+                    .any(|paddle| paddle.collides_with_aabr(laser_aabr))
+                {
+                    laser.hit = true;
+                    lives.hit(laser_aabr.center().y);
+                    camera.shake(LIFE_LOST_SHAKE);
 
                     let flash = entities.create();
-                    updater.insert(flash, ScreenFlash::new(color::RED));
+                    updater.insert(flash, ScreenFlash::new(palette.danger));
                     updater.insert(flash, Lifetime::new(5.0));
-
-                    let _ = entities.delete(entity);
                 }
             }
         }
 
-        for (player_pos, player_bb, _) in (&pos, &bb, &player).join() {
+        for (player_pos, player_bb, player, mut charge) in
+            (&pos, &bb, &player, (&mut charge).maybe()).join()
+        {
             let player_aabr = player_bb.to_aabr(player_pos);
             for (
                 entity,
@@ -231,19 +623,42 @@ impl<'a> System<'a> for ProjectileSystem {
                 let projectile_aabr = projectile_bb.to_aabr(projectile_pos);
 
                 if projectile_aabr.collides_with_aabr(player_aabr) {
-                    if upgrades.hold && input.mouse_down() {
-                        updater.insert(entity, HoldProjectile(projectile_pos.0 - player_pos.0));
+                    // Holding wins from charging, the charge is built while it's held and used
+                    // when it's released and reflected here
+                    if upgrades.hold && player.controller().holding(&input, &remote) {
+                        updater.insert(
+                            entity,
+                            HoldProjectile {
+                                player: player.id(),
+                                offset: projectile_pos.0 - player_pos.0,
+                            },
+                        );
                         continue;
                     }
-                    let speed = projectile_vel.magnitude();
+                    // Holding the button before the hit speeds it up
+                    let charged = charge.as_mut().and_then(|charge| charge.release());
+                    let (boost, damage) = charged.unwrap_or((1.0, 1));
+
+                    let speed = projectile_vel.magnitude() * boost;
                     let angle = (projectile_pos.0 - player_aabr.center() - Vec2::new(-20.0, 0.0))
                         .normalized();
                     projectile_vel.0 = angle * speed;
+                    updater.insert(entity, ReflectedBy(player.id()));
+
+                    // Steer towards the enemies with a new tank
+                    if let Some(missile) = missile.get_mut(entity) {
+                        missile.refuel();
+                    }
+
+                    if charged.is_some() {
+                        updater.insert(entity, Damage(damage));
+                        updater.insert(entity, trail(projectile_bb, true, &palette));
+                    }
 
                     let angle_rad = angle.y.atan2(angle.x);
 
                     if upgrades.split {
-                        if let Some(ref sprite) = projectile_split_into {
+                        if let Some(sprite) = projectile_split_into {
                             // Delete the source
                             let _ = entities.delete(entity);
 
@@ -252,9 +667,11 @@ impl<'a> System<'a> for ProjectileSystem {
 
                                 let new_projectile = entities.create();
                                 updater.insert(new_projectile, Projectile);
-                                updater.insert(new_projectile, Sprite::new(sprite.0.clone()));
+                                updater.insert(new_projectile, sprite.0.sprite());
+                                updater.insert(new_projectile, sprite.0.id());
                                 updater.insert(new_projectile, projectile_pos.clone());
                                 updater.insert(new_projectile, projectile_bb.clone());
+                                updater.insert(new_projectile, ReflectedBy(player.id()));
 
                                 updater.insert(
                                     new_projectile,
@@ -263,10 +680,11 @@ impl<'a> System<'a> for ProjectileSystem {
 
                                 updater.insert(
                                     new_projectile,
-                                    ParticleEmitter::new(3.0, sprites.white_particle.clone())
-                                        .with_dispersion(1.0)
-                                        .with_offset(projectile_bb.center_offset()),
+                                    trail(projectile_bb, charged.is_some(), &palette),
                                 );
+                                if charged.is_some() {
+                                    updater.insert(new_projectile, Damage(damage));
+                                }
                             }
                         }
                     }
@@ -275,3 +693,23 @@ impl<'a> System<'a> for ProjectileSystem {
         }
     }
 }
+
+/// The particle trail behind a reflected projectile, charged ones leave a longer colored trail.
+fn trail(bb: &BoundingBox, charged: bool, palette: &Palette) -> ParticleEmitter {
+    if charged {
+        ParticleEmitter::new(12.0, palette.friendly_projectile)
+            .with_rate(120.0)
+            .with_gradient(palette.friendly_projectile, palette.ui)
+            .with_fade()
+            .with_size(2.0, 1.0)
+            .with_lifetime_jitter(0.3)
+            .with_dispersion(0.3)
+            .with_offset(bb.center_offset())
+    } else {
+        ParticleEmitter::new(5.0, palette.enemy_projectile)
+            .with_gradient(palette.enemy_projectile, palette.ui_background)
+            .with_lifetime_jitter(0.3)
+            .with_dispersion(0.5)
+            .with_offset(bb.center_offset())
+    }
+}
@@ -16,7 +16,7 @@ pub fn bool() -> bool {
     r > u32::MAX / 2
 }
 
-pub fn index<T>(v: &Vec<T>) -> &T {
+pub fn index<T>(v: &[T]) -> &T {
     let len = v.len();
     let r = quad_rand::rand() as usize / (u32::MAX as usize / len);
 
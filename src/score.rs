@@ -0,0 +1,36 @@
+/// Scrap earned by each individual player, the wallet is shared.
+#[derive(Debug, Default, Clone)]
+pub struct Scores(Vec<usize>);
+
+impl Scores {
+    pub fn new(scores: Vec<usize>) -> Self {
+        Self(scores)
+    }
+
+    /// The scrap of every player.
+    pub fn all(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// Start counting again for a new amount of players.
+    pub fn reset(&mut self, players: usize) {
+        self.0 = vec![0; players];
+    }
+
+    /// Credit a player with some scrap.
+    pub fn add(&mut self, player: u8, amount: usize) {
+        if let Some(score) = self.0.get_mut(player as usize) {
+            *score += amount;
+        }
+    }
+
+    /// A line of text with the scores of all players.
+    pub fn summary(&self) -> String {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, score)| format!("P{} {}", index + 1, score))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{enemy::EnemyType, physics::*, player::Player, projectile::ProjectileEmitter};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use specs_blit::specs::*;
+use std::{collections::HashMap, fs, path::PathBuf, time::SystemTime};
+
+type Vec2 = vek::Vec2<f64>;
+
+/// Directory containing the scripts, named after the enemy archetype like `small.rhai`.
+const SCRIPT_DIR: &str = "scripts";
+/// Frames between checking whether the scripts changed on disk.
+const RELOAD_INTERVAL: usize = 60;
+/// Stop scripts that are stuck in a loop.
+const MAX_OPERATIONS: u64 = 10_000;
+
+/// A compiled script with the time it was changed on disk.
+struct LoadedScript {
+    modified: Option<SystemTime>,
+    /// Nothing when the first version didn't compile.
+    ast: Option<AST>,
+}
+
+/// The scripts for every enemy archetype that has one.
+pub struct Scripts {
+    engine: Engine,
+    scripts: HashMap<EnemyType, LoadedScript>,
+    ticks_since_reload: usize,
+    /// Whether changes on disk are loaded while playing, turned off in online games because
+    /// the other player wouldn't get them.
+    hot_reload: bool,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let mut scripts = Self {
+            engine,
+            scripts: HashMap::new(),
+            ticks_since_reload: 0,
+            hot_reload: true,
+        };
+        scripts.reload();
+
+        scripts
+    }
+}
+
+impl Scripts {
+    /// Whether the enemy archetype is controlled by a script.
+    pub fn has(&self, archetype: EnemyType) -> bool {
+        self.ast(archetype).is_some()
+    }
+
+    /// Compile the scripts that changed on disk, the old version is kept when it doesn't compile.
+    pub fn reload(&mut self) {
+        for archetype in EnemyType::all() {
+            let path = Scripts::path(*archetype);
+            let modified = match fs::metadata(&path) {
+                Ok(metadata) => metadata.modified().ok(),
+                Err(_) => {
+                    // The script got removed
+                    self.scripts.remove(archetype);
+                    continue;
+                }
+            };
+
+            let script = self
+                .scripts
+                .entry(*archetype)
+                .or_insert_with(|| LoadedScript {
+                    modified: None,
+                    ast: None,
+                });
+            if script.modified.is_some() && script.modified == modified {
+                continue;
+            }
+            script.modified = modified;
+
+            // Rhai can't read files itself on the web
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    eprintln!("Could not read script {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            match self.engine.compile(source) {
+                Ok(ast) => script.ast = Some(ast),
+                Err(err) => eprintln!("Could not load script {}: {}", path.display(), err),
+            }
+        }
+    }
+
+    /// Turn loading the changes on disk while playing on or off.
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    fn ast(&self, archetype: EnemyType) -> Option<&AST> {
+        self.scripts
+            .get(&archetype)
+            .and_then(|script| script.ast.as_ref())
+    }
+
+    fn path(archetype: EnemyType) -> PathBuf {
+        PathBuf::from(SCRIPT_DIR).join(format!("{}.rhai", archetype.name()))
+    }
+}
+
+/// What the script of an enemy can see and change.
+#[derive(Debug)]
+struct Context {
+    pos: Vec2,
+    vel: Vec2,
+    player: Vec2,
+    time: f64,
+}
+
+/// An enemy controlled by the script of its archetype.
+#[derive(Component, Debug)]
+pub struct Script {
+    archetype: EnemyType,
+    /// Frames since the enemy spawned.
+    time: f64,
+    /// Values the script wants to remember between frames.
+    state: Map,
+}
+
+impl Script {
+    pub fn new(archetype: EnemyType) -> Self {
+        Self {
+            archetype,
+            time: 0.0,
+            state: Map::new(),
+        }
+    }
+
+    /// Run the script, returns the new velocity and whether it wants to shoot.
+    fn run(&mut self, scripts: &Scripts, context: Context) -> Result<(Vec2, bool), String> {
+        let ast = match scripts.ast(self.archetype) {
+            Some(ast) => ast,
+            // The script got removed, keep moving in the same direction
+            None => return Ok((context.vel, false)),
+        };
+
+        let mut scope = Scope::new();
+        scope.push("x", context.pos.x);
+        scope.push("y", context.pos.y);
+        scope.push("vx", context.vel.x);
+        scope.push("vy", context.vel.y);
+        scope.push("player_x", context.player.x);
+        scope.push("player_y", context.player.y);
+        scope.push("time", context.time);
+        scope.push("shoot", false);
+        scope.push("state", std::mem::take(&mut self.state));
+
+        let result = scripts.engine.run_ast_with_scope(&mut scope, ast);
+
+        self.state = scope.get_value::<Map>("state").unwrap_or_default();
+        result.map_err(|err| err.to_string())?;
+
+        let float = |name: &str, default: f64| {
+            scope
+                .get_value::<Dynamic>(name)
+                .and_then(|value| value.as_float().ok())
+                .unwrap_or(default)
+        };
+
+        Ok((
+            Vec2::new(float("vx", context.vel.x), float("vy", context.vel.y)),
+            scope.get_value::<bool>("shoot").unwrap_or(false),
+        ))
+    }
+}
+
+/// System that runs the enemy scripts.
+pub struct ScriptSystem;
+impl<'a> System<'a> for ScriptSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Scripts>,
+        WriteStorage<'a, Script>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, BoundingBox>,
+        WriteStorage<'a, ProjectileEmitter>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut scripts, mut script, player, pos, mut vel, bb, mut emitter, updater): Self::SystemData,
+    ) {
+        scripts.ticks_since_reload += 1;
+        if scripts.hot_reload && scripts.ticks_since_reload >= RELOAD_INTERVAL {
+            scripts.ticks_since_reload = 0;
+            scripts.reload();
+        }
+
+        let players = (&player, &pos, &bb)
+            .join()
+            .map(|(_, pos, bb)| pos.0 + bb.center_offset())
+            .collect::<Vec<_>>();
+
+        for (entity, script, pos, vel, emitter) in (
+            &*entities,
+            &mut script,
+            &pos,
+            &mut vel,
+            (&mut emitter).maybe(),
+        )
+            .join()
+        {
+            script.time += 1.0;
+
+            // Look at the paddle closest by
+            let player = players
+                .iter()
+                .min_by(|a, b| (a.y - pos.y).abs().total_cmp(&(b.y - pos.y).abs()))
+                .copied()
+                .unwrap_or_default();
+
+            let context = Context {
+                pos: pos.0,
+                vel: vel.0,
+                player,
+                time: script.time,
+            };
+            match script.run(&scripts, context) {
+                Ok((new_vel, shoot)) => {
+                    vel.0 = new_vel;
+
+                    if shoot {
+                        if let Some(emitter) = emitter {
+                            emitter.trigger();
+                        }
+                    }
+                }
+                Err(err) => {
+                    // Don't keep running a broken script every frame
+                    eprintln!(
+                        "Script for {} enemy failed: {}",
+                        script.archetype.name(),
+                        err
+                    );
+                    updater.remove::<Script>(entity);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,592 @@
+//! Broadcasting games to spectators.
+//!
+//! Every tick a snapshot is sent with the positions of the sprites, the particles, the damage
+//! of the planet, the seed and time of the background and the HUD. The pixels of a sprite are
+//! sent once when the spectator doesn't know it yet.
+//!
+//! Not everything is sent: the comets in the background, the bars of the upgrades, the charge
+//! of the paddles and the shop are only shown in the game itself. With a lot of particles only
+//! some of them are sent. The screen effects and colors are the ones picked by the spectator.
+use crate::{
+    background::Background,
+    color::Palette,
+    effect::{Camera, ScreenFlash},
+    enemy::EnemiesLeft,
+    gui::Gui,
+    lives::Lives,
+    money::Wallet,
+    particle::{Dot, Particles},
+    phase::Phase,
+    planet::{self, Planet},
+    score::Scores,
+    sprite::{self, SpriteId},
+    versus::Commander,
+};
+use anyhow::{bail, Result};
+use specs_blit::{
+    blit::{BlitBuffer, Color},
+    specs::{Join, World, WorldExt},
+    PixelBuffer, Sprite,
+};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// The port spectators connect to.
+pub const SPECTATOR_PORT: u16 = 7047;
+
+/// Spectators that can't keep up and have this many bytes waiting are disconnected.
+const MAX_BACKLOG: usize = 1 << 20;
+/// Particles sent in a snapshot at most, with more only some of every explosion are sent.
+const MAX_DOTS: usize = 512;
+/// How long to wait for the game when connecting.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const MESSAGE_SPRITE: u8 = 0;
+const MESSAGE_SNAPSHOT: u8 = 1;
+
+/// What's happening in the game, the spectator doesn't need all the phases.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+enum Stage {
+    #[default]
+    Menu,
+    Playing,
+    Shop,
+    GameOver,
+}
+
+impl Stage {
+    fn from_phase(phase: &Phase) -> Self {
+        match phase {
+            Phase::Menu | Phase::Initialize | Phase::Editor => Stage::Menu,
+            Phase::Setup => Stage::Shop,
+            Phase::GameOver => Stage::GameOver,
+            Phase::Play | Phase::WaitingForLastEnemy | Phase::SwitchTo(_) => Stage::Playing,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Stage::Menu => 0,
+            Stage::Playing => 1,
+            Stage::Shop => 2,
+            Stage::GameOver => 3,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Stage::Playing,
+            2 => Stage::Shop,
+            3 => Stage::GameOver,
+            _ => Stage::Menu,
+        }
+    }
+}
+
+/// Everything a spectator needs to draw a single frame.
+#[derive(Debug, Default, Clone)]
+pub struct Snapshot {
+    stage: Stage,
+    level: u16,
+    lives: u8,
+    /// Lives lost since the start, the planet gets darker with every one.
+    lost: u8,
+    scrap: u32,
+    enemies: u16,
+    /// The color the screen is flashing with.
+    flash: Option<u32>,
+    /// The offset of the shaking camera.
+    camera: (i16, i16),
+    /// The seed the background is generated from and the frame it's at.
+    background: (u64, u32),
+    /// The scrap of every player, only shown with more than one player.
+    scores: Scores,
+    /// The energy and cursor of the second player in versus mode.
+    commander: Option<(f32, f32)>,
+    /// The heights of the craters in the planet.
+    craters: Vec<i16>,
+    /// The particles as they're drawn.
+    dots: Vec<Dot>,
+    /// The rendered sprites with their position.
+    sprites: Vec<(SpriteId, i16, i16)>,
+}
+
+impl Snapshot {
+    /// Take a snapshot of what's currently rendered.
+    pub fn capture(world: &World, level: usize, background: &Background) -> Self {
+        let sprites = (
+            &world.read_storage::<Sprite>(),
+            &world.read_storage::<SpriteId>(),
+        )
+            .join()
+            .map(|(sprite, id)| {
+                let (x, y) = sprite.pos();
+
+                (*id, x as i16, y as i16)
+            })
+            .collect();
+
+        // Skip evenly through the particles so every explosion still shows up
+        let particles = world.read_resource::<Particles>();
+        let dots = particles
+            .dots()
+            .step_by(particles.len().div_ceil(MAX_DOTS).max(1))
+            .collect();
+
+        let (camera_x, camera_y) = world.read_resource::<Camera>().offset();
+        let lives = world.try_fetch::<Lives>();
+
+        Self {
+            stage: Stage::from_phase(&world.read_resource::<Phase>()),
+            level: level as u16,
+            lives: lives.as_ref().map(|lives| lives.amount()).unwrap_or(0),
+            lost: lives.as_ref().map(|lives| lives.lost() as u8).unwrap_or(0),
+            scrap: world.read_resource::<Wallet>().money() as u32,
+            enemies: world.read_resource::<EnemiesLeft>().0 as u16,
+            flash: world
+                .read_storage::<ScreenFlash>()
+                .join()
+                .last()
+                .map(ScreenFlash::color),
+            camera: (camera_x as i16, camera_y as i16),
+            background: (background.origin(), background.time() as u32),
+            scores: (*world.read_resource::<Scores>()).clone(),
+            commander: world.try_fetch::<Commander>().map(|commander| {
+                let (energy, cursor_y) = commander.state();
+
+                (energy as f32, cursor_y as f32)
+            }),
+            craters: world
+                .read_storage::<Planet>()
+                .join()
+                .flat_map(Planet::craters)
+                .map(|y| y as i16)
+                .collect(),
+            dots,
+            sprites,
+        }
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(MESSAGE_SNAPSHOT);
+        buf.push(self.stage.to_u8());
+        buf.extend_from_slice(&self.level.to_le_bytes());
+        buf.push(self.lives);
+        buf.push(self.lost);
+        buf.extend_from_slice(&self.scrap.to_le_bytes());
+        buf.extend_from_slice(&self.enemies.to_le_bytes());
+        // A completely transparent flash can't happen so it's used for no flash
+        buf.extend_from_slice(&self.flash.unwrap_or(0).to_le_bytes());
+        buf.extend_from_slice(&self.camera.0.to_le_bytes());
+        buf.extend_from_slice(&self.camera.1.to_le_bytes());
+        buf.extend_from_slice(&self.background.0.to_le_bytes());
+        buf.extend_from_slice(&self.background.1.to_le_bytes());
+
+        buf.push(self.scores.all().len() as u8);
+        for score in self.scores.all() {
+            buf.extend_from_slice(&(*score as u32).to_le_bytes());
+        }
+
+        match self.commander {
+            Some((energy, cursor_y)) => {
+                buf.push(1);
+                buf.extend_from_slice(&energy.to_le_bytes());
+                buf.extend_from_slice(&cursor_y.to_le_bytes());
+            }
+            None => buf.push(0),
+        }
+
+        // Anything that doesn't fit in the count is left out
+        let craters = self.craters.len().min(u8::MAX as usize);
+        buf.push(craters as u8);
+        for y in self.craters.iter().take(craters) {
+            buf.extend_from_slice(&y.to_le_bytes());
+        }
+
+        let dots = self.dots.len().min(u16::MAX as usize);
+        buf.extend_from_slice(&(dots as u16).to_le_bytes());
+        for dot in self.dots.iter().take(dots) {
+            buf.extend_from_slice(&(dot.x as i16).to_le_bytes());
+            buf.extend_from_slice(&(dot.y as i16).to_le_bytes());
+            buf.push(dot.size.min(u8::MAX as i32) as u8);
+            buf.push((dot.alpha * 255.0).round() as u8);
+            buf.extend_from_slice(&dot.color.to_le_bytes());
+        }
+
+        let sprites = self.sprites.len().min(u16::MAX as usize);
+        buf.extend_from_slice(&(sprites as u16).to_le_bytes());
+        for (id, x, y) in self.sprites.iter().take(sprites) {
+            buf.extend_from_slice(&id.0.to_le_bytes());
+            buf.extend_from_slice(&x.to_le_bytes());
+            buf.extend_from_slice(&y.to_le_bytes());
+        }
+    }
+
+    /// Read the snapshot after the message type, returns nothing if it's not complete yet.
+    fn decode(buf: &[u8]) -> Option<(Self, usize)> {
+        // Skip the message type
+        let mut reader = Reader { buf, pos: 1 };
+
+        let stage = Stage::from_u8(reader.u8()?);
+        let level = reader.u16()?;
+        let lives = reader.u8()?;
+        let lost = reader.u8()?;
+        let scrap = reader.u32()?;
+        let enemies = reader.u16()?;
+        let flash = reader.u32()?;
+        let camera = (reader.u16()? as i16, reader.u16()? as i16);
+        let background = (reader.u64()?, reader.u32()?);
+
+        let scores = (0..reader.u8()?)
+            .map(|_| reader.u32().map(|score| score as usize))
+            .collect::<Option<_>>()?;
+
+        let commander = match reader.u8()? {
+            0 => None,
+            _ => Some((reader.f32()?, reader.f32()?)),
+        };
+
+        let craters = (0..reader.u8()?)
+            .map(|_| reader.u16().map(|y| y as i16))
+            .collect::<Option<_>>()?;
+
+        let dots = (0..reader.u16()?)
+            .map(|_| {
+                Some(Dot {
+                    x: reader.u16()? as i16 as i32,
+                    y: reader.u16()? as i16 as i32,
+                    size: reader.u8()? as i32,
+                    alpha: reader.u8()? as f64 / 255.0,
+                    color: reader.u32()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let sprites = (0..reader.u16()?)
+            .map(|_| {
+                Some((
+                    SpriteId(reader.u16()?),
+                    reader.u16()? as i16,
+                    reader.u16()? as i16,
+                ))
+            })
+            .collect::<Option<_>>()?;
+
+        Some((
+            Self {
+                stage,
+                level,
+                lives,
+                lost,
+                scrap,
+                enemies,
+                flash: if flash == 0 { None } else { Some(flash) },
+                camera,
+                background,
+                scores: Scores::new(scores),
+                commander,
+                craters,
+                dots,
+                sprites,
+            },
+            reader.pos,
+        ))
+    }
+}
+
+/// Reads little endian numbers from a message that might not be complete yet.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// The next bytes, nothing when they haven't been received yet.
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.buf.get(self.pos..self.pos + N)?.try_into().ok()?;
+        self.pos += N;
+
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes().map(u8::from_le_bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.bytes().map(f32::from_le_bytes)
+    }
+}
+
+/// A spectator connected to the server.
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    /// The amount of sprites this spectator already received.
+    sprites_sent: usize,
+    /// Bytes that couldn't be written yet.
+    outgoing: Vec<u8>,
+}
+
+impl Client {
+    /// Queue the sprites the spectator doesn't know yet and a snapshot, and send as much as
+    /// possible.
+    fn send(&mut self, snapshot: &Snapshot) -> Result<()> {
+        for (id, width, pixels) in sprite::loaded_since(self.sprites_sent) {
+            self.outgoing.push(MESSAGE_SPRITE);
+            self.outgoing.extend_from_slice(&id.0.to_le_bytes());
+            self.outgoing
+                .extend_from_slice(&(width as u16).to_le_bytes());
+            self.outgoing
+                .extend_from_slice(&(pixels.len() as u32).to_le_bytes());
+            for pixel in pixels {
+                self.outgoing.extend_from_slice(&pixel.to_le_bytes());
+            }
+
+            self.sprites_sent += 1;
+        }
+
+        snapshot.encode(&mut self.outgoing);
+
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => bail!("connection closed"),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if self.outgoing.len() > MAX_BACKLOG {
+            bail!("spectator can't keep up");
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends what's happening in the game to everyone who wants to watch.
+#[derive(Debug)]
+pub struct SpectatorServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl SpectatorServer {
+    /// Start listening for spectators.
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", SPECTATOR_PORT))?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener,
+            clients: vec![],
+        })
+    }
+
+    /// Amount of people watching.
+    pub fn spectators(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Send the snapshot to all spectators, accepting new ones and dropping the ones that left.
+    pub fn broadcast(&mut self, snapshot: &Snapshot) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() && stream.set_nodelay(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    sprites_sent: 0,
+                    outgoing: vec![],
+                });
+            }
+        }
+
+        self.clients
+            .retain_mut(|client| client.send(snapshot).is_ok());
+    }
+}
+
+/// Watches a game running on another machine without simulating anything.
+pub struct Spectator {
+    stream: TcpStream,
+    /// Received bytes that don't form a full message yet.
+    incoming: Vec<u8>,
+    sprites: HashMap<SpriteId, BlitBuffer>,
+    /// The last received snapshot.
+    snapshot: Snapshot,
+    lives: Lives,
+    /// The background of the watched game, generated again when its seed changes.
+    background: Option<Background>,
+}
+
+impl Spectator {
+    /// Connect to a game that's broadcasting.
+    pub fn connect(address: &str) -> Result<Self> {
+        let address = match address.to_socket_addrs()?.next() {
+            Some(address) => address,
+            None => bail!("invalid address"),
+        };
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            incoming: vec![],
+            sprites: HashMap::new(),
+            snapshot: Snapshot::default(),
+            lives: Lives::new(0),
+            background: None,
+        })
+    }
+
+    /// Read everything that's sent and keep the latest snapshot.
+    pub fn update(&mut self) -> Result<()> {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => bail!("connection closed"),
+                Ok(read) => self.incoming.extend_from_slice(&buf[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut start = 0;
+        loop {
+            let buf = &self.incoming[start..];
+            let size = match buf.first() {
+                Some(&MESSAGE_SPRITE) => {
+                    if buf.len() < 9 {
+                        break;
+                    }
+
+                    let id = SpriteId(u16::from_le_bytes([buf[1], buf[2]]));
+                    let width = u16::from_le_bytes([buf[3], buf[4]]) as i32;
+                    let len = u32::from_le_bytes([buf[5], buf[6], buf[7], buf[8]]) as usize;
+                    let size = 9 + len * 4;
+                    if buf.len() < size {
+                        break;
+                    }
+
+                    let pixels = buf[9..size]
+                        .chunks_exact(4)
+                        .map(|p| u32::from_le_bytes([p[0], p[1], p[2], p[3]]))
+                        .collect::<Vec<_>>();
+                    self.sprites.insert(
+                        id,
+                        BlitBuffer::from_buffer(&pixels, width.max(1), Color::from_u32(0)),
+                    );
+
+                    size
+                }
+                Some(&MESSAGE_SNAPSHOT) => match Snapshot::decode(buf) {
+                    Some((snapshot, size)) => {
+                        self.snapshot = snapshot;
+
+                        size
+                    }
+                    None => break,
+                },
+                Some(other) => bail!("Received unknown message type {}", other),
+                None => break,
+            };
+
+            start += size;
+        }
+        self.incoming.drain(..start);
+
+        self.lives.set_amount(self.snapshot.lives);
+
+        let (seed, time) = self.snapshot.background;
+        if self.background.as_ref().map(Background::origin) != Some(seed) {
+            self.background = Some(Background::new(seed));
+        }
+        if let Some(background) = self.background.as_mut() {
+            background.set_time(time as f64);
+        }
+
+        Ok(())
+    }
+
+    /// Draw the last received snapshot.
+    pub fn render(&self, buffer: &mut PixelBuffer, gui: &mut Gui, palette: &Palette) {
+        let snapshot = &self.snapshot;
+        let camera = (snapshot.camera.0 as i32, snapshot.camera.1 as i32);
+        if let Some(background) = self.background.as_ref() {
+            background.copy(buffer.pixels_mut(), camera);
+        }
+
+        match snapshot.stage {
+            Stage::Menu => gui.draw_label(buffer, "Waiting for the game to start...", 80, 145),
+            Stage::Shop => gui.draw_label(buffer, "Buying upgrades...", 120, 145),
+            Stage::GameOver => {
+                gui.draw_label(buffer, "GAME OVER!", 150, 130);
+                gui.draw_label(
+                    buffer,
+                    format!("Level {}\nScrap {}", snapshot.level, snapshot.scrap),
+                    165,
+                    150,
+                );
+            }
+            Stage::Playing => {
+                for dot in snapshot.dots.iter() {
+                    dot.render(buffer, camera);
+                }
+
+                let width = buffer.width();
+                for (id, x, y) in snapshot.sprites.iter() {
+                    if let Some(sprite) = self.sprites.get(id) {
+                        sprite.blit(buffer.pixels_mut(), width, (*x as i32, *y as i32));
+                    }
+                }
+
+                planet::render_damage(
+                    buffer,
+                    camera,
+                    snapshot.lost as usize,
+                    snapshot.craters.iter().map(|y| *y as f64),
+                );
+
+                if let Some(color) = snapshot.flash {
+                    buffer.clear(color);
+                }
+
+                self.lives.render(buffer, 20, 5);
+                gui.draw_label(buffer, format!("Level {}", snapshot.level), 70, 5);
+                match snapshot.commander {
+                    Some((energy, cursor_y)) => {
+                        let commander = Commander::with_state(energy as f64, cursor_y as f64);
+                        gui.draw_label(buffer, format!("Energy {}", commander.energy()), 150, 5);
+                        commander.render(buffer, palette);
+                    }
+                    None => gui.draw_label(buffer, format!("Enemies {}", snapshot.enemies), 150, 5),
+                }
+                gui.draw_label(buffer, format!("Scrap {}", snapshot.scrap), 250, 5);
+                if snapshot.scores.all().len() > 1 {
+                    gui.draw_label(buffer, snapshot.scores.summary(), 250, 17);
+                }
+            }
+        }
+
+        gui.draw_label(buffer, "Spectating, press escape to stop", 20, 285);
+    }
+}
@@ -1,20 +1,63 @@
 use crate::{
     color,
+    effect::Camera,
+    mask::Mask,
     physics::{Position, Velocity},
+    random,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use specs_blit::{
     blit::{BlitBuffer, Color},
     specs::*,
-    Sprite, SpriteRef,
+    PixelBuffer, Sprite,
 };
 use sprite_gen::{
     MaskValue::{self, *},
     Options,
 };
+use std::{convert::TryFrom, sync::Mutex};
 
 type Vec2 = vek::Vec2<f64>;
 
+/// Width of the laser sprites, long enough to cross the whole screen.
+pub const LASER_LENGTH: usize = crate::WIDTH;
+/// Height of the laser beam sprite.
+pub const LASER_HEIGHT: usize = 3;
+/// Pixels the flames of the ship engines reach at most.
+const ENGINE_FLAME_LENGTH: usize = 3;
+/// Frames in the explosion sequence and the size of the biggest one.
+const EXPLOSION_FRAMES: usize = 6;
+pub const EXPLOSION_SIZE: usize = 16;
+/// Size of the projectiles fired by the paddles.
+pub const SHOT_WIDTH: usize = 5;
+pub const SHOT_HEIGHT: usize = 2;
+
+/// The width and pixels of every loaded sprite, the index is the ID of the sprite.
+static REGISTRY: Mutex<Vec<(usize, Vec<u32>)>> = Mutex::new(Vec::new());
+
+/// The ID of a loaded sprite, used to refer to it outside of the game.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[storage(VecStorage)]
+pub struct SpriteId(pub u16);
+
+/// A reference to a loaded sprite that also knows its ID.
+#[derive(Debug, Clone)]
+pub struct SpriteRef {
+    id: SpriteId,
+    reference: specs_blit::SpriteRef,
+}
+
+impl SpriteRef {
+    pub fn id(&self) -> SpriteId {
+        self.id
+    }
+
+    /// Create the component to render this sprite.
+    pub fn sprite(&self) -> Sprite {
+        Sprite::new(self.reference.clone())
+    }
+}
+
 #[derive(Component, Debug, Default)]
 #[storage(NullStorage)]
 pub struct RotationFollowsVelocity;
@@ -35,14 +78,104 @@ impl<'a> System<'a> for SpriteRotationSystem {
     }
 }
 
-/// A system that connects sprites to the physics position.
+/// A sequence of sprites that are shown after each other.
+#[derive(Component, Debug, Clone)]
+pub struct Animation {
+    frames: Vec<SpriteRef>,
+    /// Frames of the animation shown every second.
+    frame_rate: f64,
+    /// Frames of the game since the animation started.
+    time: f64,
+    /// The frame that's currently shown.
+    current: usize,
+    /// Start again at the end instead of removing the entity.
+    looping: bool,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<SpriteRef>, frame_rate: f64) -> Self {
+        Self {
+            frames,
+            frame_rate,
+            time: 0.0,
+            current: 0,
+            looping: true,
+        }
+    }
+
+    /// Remove the entity when the last frame is done.
+    pub fn with_once(mut self) -> Self {
+        self.looping = false;
+
+        self
+    }
+
+    /// Start at a random frame so multiple animations aren't in sync.
+    pub fn with_random_start(mut self) -> Self {
+        self.time = random::range(0.0, self.frames.len() as f64 * 60.0 / self.frame_rate);
+
+        self
+    }
+
+    /// The sprite of the first frame, used to create the entity.
+    pub fn first(&self) -> &SpriteRef {
+        &self.frames[0]
+    }
+}
+
+/// A system that shows the next frames of the animations.
+pub struct AnimationSystem;
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Animation>,
+        WriteStorage<'a, Sprite>,
+        WriteStorage<'a, SpriteId>,
+    );
+
+    fn run(&mut self, (entities, mut animation, mut sprite, mut id): Self::SystemData) {
+        for (entity, animation, sprite, id) in
+            (&*entities, &mut animation, &mut sprite, &mut id).join()
+        {
+            animation.time += 1.0;
+
+            let mut frame = (animation.time * animation.frame_rate / 60.0) as usize;
+            if frame >= animation.frames.len() {
+                if !animation.looping {
+                    let _ = entities.delete(entity);
+                    continue;
+                }
+                frame %= animation.frames.len();
+            }
+
+            if frame != animation.current {
+                animation.current = frame;
+
+                // Keep where it's drawn, the position and rotation systems only update it later
+                let (pos, rot) = (sprite.pos(), sprite.rot());
+                let next = &animation.frames[frame];
+                *sprite = next.sprite();
+                sprite.set_pos(pos.0, pos.1);
+                sprite.set_rot(rot);
+                *id = next.id();
+            }
+        }
+    }
+}
+
+/// A system that connects sprites to the physics position, moved by the camera.
 pub struct SpritePositionSystem;
 impl<'a> System<'a> for SpritePositionSystem {
-    type SystemData = (ReadStorage<'a, Position>, WriteStorage<'a, Sprite>);
+    type SystemData = (
+        Read<'a, Camera>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Sprite>,
+    );
 
-    fn run(&mut self, (pos, mut sprite): Self::SystemData) {
+    fn run(&mut self, (camera, pos, mut sprite): Self::SystemData) {
+        let (x, y) = camera.offset();
         for (pos, sprite) in (&pos, &mut sprite).join() {
-            sprite.set_pos(pos.x as i32, pos.y as i32);
+            sprite.set_pos(pos.x as i32 + x, pos.y as i32 + y);
         }
     }
 }
@@ -61,16 +194,163 @@ pub fn buffer(width: usize, options: Options, mask: &[MaskValue]) -> BlitBuffer
     )
 }
 
+/// Load a sprite so it can be rendered and remember its pixels.
+pub fn load(buffer: BlitBuffer) -> Result<SpriteRef> {
+    load_rotations(buffer, 1)
+}
+
+/// Load a sprite with an amount of rotations, only the unrotated pixels are remembered.
+pub fn load_rotations(buffer: BlitBuffer, rotations: u16) -> Result<SpriteRef> {
+    let mut registry = REGISTRY.lock().unwrap();
+
+    let id = SpriteId(
+        u16::try_from(registry.len()).map_err(|_| anyhow!("Too many sprites are loaded"))?,
+    );
+    registry.push((buffer.width() as usize, buffer.to_raw_buffer()));
+
+    Ok(SpriteRef {
+        id,
+        reference: specs_blit::load_rotations(buffer, rotations)?,
+    })
+}
+
+/// The width and pixels of all sprites loaded after the first amount.
+pub fn loaded_since(amount: usize) -> Vec<(SpriteId, usize, Vec<u32>)> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .skip(amount)
+        .map(|(index, (width, pixels))| (SpriteId(index as u16), *width, pixels.clone()))
+        .collect()
+}
+
+/// The width and pixels of a loaded sprite.
+pub fn pixels(id: SpriteId) -> Option<(usize, Vec<u32>)> {
+    REGISTRY.lock().unwrap().get(id.0 as usize).cloned()
+}
+
+/// Draw the pixels of a sprite enlarged, the empty pixels are skipped.
+pub fn draw_scaled(
+    buffer: &mut PixelBuffer,
+    (x, y): (usize, usize),
+    scale: usize,
+    width: usize,
+    pixels: &[u32],
+) {
+    let (buffer_width, buffer_height) = (buffer.width(), buffer.height());
+    let target = buffer.pixels_mut();
+
+    for (index, pixel) in pixels.iter().enumerate() {
+        // The mask color only has to match in the color channels
+        if *pixel & 0xFFFFFF == 0 {
+            continue;
+        }
+
+        let (pixel_x, pixel_y) = (x + index % width * scale, y + index / width * scale);
+        for target_y in (pixel_y..pixel_y + scale).take_while(|y| *y < buffer_height) {
+            for target_x in (pixel_x..pixel_x + scale).take_while(|x| *x < buffer_width) {
+                target[target_y * buffer_width + target_x] = *pixel;
+            }
+        }
+    }
+}
+
+/// Frames of a ship with flickering flames coming out of the back of its engine.
+pub fn engine_flicker(buffer: BlitBuffer, frames: usize) -> Result<Vec<SpriteRef>> {
+    let (width, height) = (buffer.width() as usize, buffer.height() as usize);
+    let pixels = buffer.to_raw_buffer();
+    let flame_width = width + ENGINE_FLAME_LENGTH;
+
+    // The ships point to the left, the engine is at the right side
+    let engine_rows = (0..height)
+        .filter_map(|y| {
+            let row = &pixels[y * width..(y + 1) * width];
+            row.iter()
+                .rposition(|pixel| *pixel != 0)
+                .filter(|x| *x + ENGINE_FLAME_LENGTH >= width)
+                .map(|x| (y, x + 1))
+        })
+        .collect::<Vec<_>>();
+
+    (0..frames)
+        .map(|_| {
+            let mut frame = vec![0; flame_width * height];
+            for (y, row) in pixels.chunks_exact(width).enumerate() {
+                frame[y * flame_width..y * flame_width + width].copy_from_slice(row);
+            }
+
+            for (y, start) in engine_rows.iter() {
+                let length = random::range(1.0, ENGINE_FLAME_LENGTH as f64 + 1.0) as usize;
+                for i in 0..length {
+                    frame[y * flame_width + start + i] = if i == 0 {
+                        color::FOREGROUND
+                    } else if i + 1 < length {
+                        color::ORANGE
+                    } else {
+                        color::RED
+                    };
+                }
+            }
+
+            load(BlitBuffer::from_buffer(
+                &frame,
+                flame_width as i32,
+                Color::from_u32(0),
+            ))
+        })
+        .collect()
+}
+
+/// Frames of a sprite spinning around its vertical axis.
+pub fn spin(buffer: BlitBuffer, frames: usize) -> Result<Vec<SpriteRef>> {
+    let (width, height) = (buffer.width() as usize, buffer.height() as usize);
+    let pixels = buffer.to_raw_buffer();
+    let center = width as f64 / 2.0;
+
+    (0..frames)
+        .map(|frame| {
+            // Never completely flat so it doesn't disappear
+            let angle = frame as f64 / frames as f64 * std::f64::consts::TAU;
+            let scale = angle.cos().abs().max(0.2);
+            // Show the mirrored back side during the second half
+            let mirrored = angle.cos() < 0.0;
+
+            let spun = (0..width * height)
+                .map(|index| {
+                    let (x, y) = (index % width, index / width);
+                    let source = center + (x as f64 + 0.5 - center) / scale;
+                    if source < 0.0 || source >= width as f64 {
+                        return 0;
+                    }
+
+                    let source = source as usize;
+                    let source = if mirrored { width - 1 - source } else { source };
+
+                    pixels[y * width + source]
+                })
+                .collect::<Vec<_>>();
+
+            load(BlitBuffer::from_buffer(
+                &spun,
+                width as i32,
+                Color::from_u32(0),
+            ))
+        })
+        .collect()
+}
+
 /// Generate a random sprite from a mask and return it as a blit buffer.
 pub fn generate(width: usize, options: Options, mask: &[MaskValue]) -> Result<SpriteRef> {
-    specs_blit::load(buffer(width, options, mask))
+    load(buffer(width, options, mask))
 }
 
 /// Generate a single pixel sprite.
 pub fn single_pixel(color: Color) -> Result<SpriteRef> {
     let buf = BlitBuffer::from_buffer(&[color.u32()], 1, Color::from_u32(0));
 
-    specs_blit::load(buf)
+    load(buf)
 }
 
 pub fn generate_planet() -> Result<SpriteRef> {
@@ -106,40 +386,74 @@ pub fn generate_planet() -> Result<SpriteRef> {
 }
 
 pub struct Sprites {
+    /// Shared by all paddles so spawning doesn't load a new sprite every time.
+    pub paddle: SpriteRef,
+    pub paddle_width: f64,
+    pub paddle_height: f64,
     pub red_particle: SpriteRef,
-    pub white_particle: SpriteRef,
     pub big_projectile: SpriteRef,
     pub big_projectile_width: f64,
     pub big_projectile_height: f64,
     pub small_projectile: SpriteRef,
     pub small_projectile_width: f64,
     pub small_projectile_height: f64,
+    /// Fired by the gun of the paddles.
+    pub shot: SpriteRef,
+    pub missile: SpriteRef,
+    pub missile_width: f64,
+    pub missile_height: f64,
+    /// The line shown before a laser fires.
+    pub laser_warning: SpriteRef,
+    pub laser: SpriteRef,
     pub planet: SpriteRef,
+    /// A fireball that grows and burns out.
+    pub explosion: Vec<SpriteRef>,
 }
 
 impl Sprites {
     pub fn generate() -> Result<Self> {
-        let white_particle = single_pixel(Color::from_u32(color::FOREGROUND))?;
+        let (paddle, paddle_width, paddle_height) = Sprites::generate_paddle()?;
         let red_particle = single_pixel(Color::from_u32(color::RED))?;
         let (big_projectile, big_projectile_width, big_projectile_height) =
             Sprites::generate_big_projectile()?;
         let (small_projectile, small_projectile_width, small_projectile_height) =
             Sprites::generate_small_projectile()?;
+        let shot = load(BlitBuffer::from_buffer(
+            &[color::GREEN; SHOT_WIDTH * SHOT_HEIGHT],
+            SHOT_WIDTH as i32,
+            Color::from_u32(0),
+        ))?;
+        let (missile, missile_width, missile_height) = Sprites::generate_missile()?;
+        let (laser_warning, laser) = Sprites::generate_laser()?;
         let planet = generate_planet()?;
+        let explosion = Sprites::generate_explosion()?;
 
         Ok(Self {
+            paddle,
+            paddle_width,
+            paddle_height,
             red_particle,
-            white_particle,
             big_projectile,
             big_projectile_width,
             big_projectile_height,
             small_projectile,
             small_projectile_width,
             small_projectile_height,
+            shot,
+            missile,
+            missile_width,
+            missile_height,
+            laser_warning,
+            laser,
             planet,
+            explosion,
         })
     }
 
+    pub fn paddle(&self) -> (SpriteRef, f64, f64) {
+        (self.paddle.clone(), self.paddle_width, self.paddle_height)
+    }
+
     pub fn big_projectile(&self) -> (SpriteRef, f64, f64) {
         (
             self.big_projectile.clone(),
@@ -156,56 +470,113 @@ impl Sprites {
         )
     }
 
+    pub fn missile(&self) -> (SpriteRef, f64, f64) {
+        (
+            self.missile.clone(),
+            self.missile_width,
+            self.missile_height,
+        )
+    }
+
     fn generate_big_projectile() -> Result<(SpriteRef, f64, f64)> {
-        let (width, height, options) = (
-            4,
-            4,
-            Options {
-                mirror_x: true,
-                mirror_y: true,
-                colored: true,
-                edge_brightness: 0.0,
-                color_variations: 0.8547504,
-                brightness_noise: 0.9012264,
-                saturation: 1.0,
-                seed: quad_rand::rand() as u64,
-            },
-        );
-        let data = [
-            Empty, Empty, Empty, Body1, Empty, Body1, Body2, Body2, Empty, Body2, Body2, Body2,
-            Body1, Body2, Body2, Body2,
-        ];
+        let mask = Mask::load("big_projectile")?;
 
         Ok((
-            generate(width, options, &data)?,
-            width as f64 * 2.0,
-            height as f64 * 2.0,
+            load(mask.buffer())?,
+            mask.width() as f64 * 2.0,
+            mask.height() as f64 * 2.0,
         ))
     }
 
     fn generate_small_projectile() -> Result<(SpriteRef, f64, f64)> {
-        let (width, height, options) = (
-            3,
-            3,
-            Options {
-                mirror_x: true,
-                mirror_y: true,
-                colored: true,
-                edge_brightness: 0.0,
-                color_variations: 0.8547504,
-                brightness_noise: 0.9012264,
-                saturation: 1.0,
-                seed: quad_rand::rand() as u64,
-            },
-        );
-        let data = [
-            Empty, Empty, Empty, Empty, Body1, Body2, Empty, Body2, Body2,
-        ];
+        let mask = Mask::load("small_projectile")?;
+
+        Ok((
+            load(mask.buffer())?,
+            mask.width() as f64 * 2.0,
+            mask.height() as f64 * 2.0,
+        ))
+    }
+
+    fn generate_paddle() -> Result<(SpriteRef, f64, f64)> {
+        let mask = Mask::load("paddle")?;
+
+        Ok((
+            load(mask.buffer())?,
+            mask.width() as f64,
+            mask.height() as f64 * 2.0,
+        ))
+    }
+
+    fn generate_missile() -> Result<(SpriteRef, f64, f64)> {
+        let mask = Mask::load("missile")?;
+
+        Ok((
+            // Rotated to follow the velocity when steering
+            load_rotations(mask.buffer(), 32)?,
+            mask.width() as f64,
+            mask.height() as f64 * 2.0,
+        ))
+    }
+
+    /// Rings of fire growing from the center, the later frames have more holes.
+    fn generate_explosion() -> Result<Vec<SpriteRef>> {
+        let center = EXPLOSION_SIZE as f64 / 2.0;
+
+        (0..EXPLOSION_FRAMES)
+            .map(|frame| {
+                let progress = (frame + 1) as f64 / EXPLOSION_FRAMES as f64;
+                let radius = center * progress;
+
+                let pixels = (0..EXPLOSION_SIZE * EXPLOSION_SIZE)
+                    .map(|index| {
+                        let (x, y) = (index % EXPLOSION_SIZE, index / EXPLOSION_SIZE);
+                        let distance = Vec2::new(x as f64 + 0.5 - center, y as f64 + 0.5 - center)
+                            .magnitude()
+                            / radius;
+
+                        if distance > 1.0 || random::range(0.0, 1.0) < progress * progress {
+                            0
+                        } else if distance < 0.5 - progress * 0.5 {
+                            color::FOREGROUND
+                        } else if distance < 0.8 {
+                            color::ORANGE
+                        } else {
+                            color::RED
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                load(BlitBuffer::from_buffer(
+                    &pixels,
+                    EXPLOSION_SIZE as i32,
+                    Color::from_u32(0),
+                ))
+            })
+            .collect()
+    }
+
+    /// A dashed warning line and the beam itself, both as wide as the screen.
+    fn generate_laser() -> Result<(SpriteRef, SpriteRef)> {
+        let warning = (0..LASER_LENGTH)
+            .map(|x| if x % 4 < 2 { color::RED } else { 0 })
+            .collect::<Vec<_>>();
+        let beam = [color::RED, color::FOREGROUND, color::RED]
+            .iter()
+            .flat_map(|color| std::iter::repeat_n(*color, LASER_LENGTH))
+            .collect::<Vec<_>>();
 
         Ok((
-            generate(width, options, &data)?,
-            width as f64 * 2.0,
-            height as f64 * 2.0,
+            load(BlitBuffer::from_buffer(
+                &warning,
+                LASER_LENGTH as i32,
+                Color::from_u32(0),
+            ))?,
+            load(BlitBuffer::from_buffer(
+                &beam,
+                LASER_LENGTH as i32,
+                Color::from_u32(0),
+            ))?,
         ))
     }
 }
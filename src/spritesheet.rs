@@ -0,0 +1,115 @@
+use crate::{
+    enemy::EnemyType,
+    gui::Gui,
+    lives, pickup,
+    ship::Ships,
+    sprite::{self, SpriteRef, Sprites},
+};
+use anyhow::{anyhow, Result};
+use specs_blit::PixelBuffer;
+
+/// Every sprite pixel is drawn as a square of this size.
+const SCALE: usize = 2;
+/// Pixels between the sprites and around the sheet.
+const PADDING: usize = 6;
+/// Width of the column with the names, fits 18 characters of the font.
+const LABEL_WIDTH: usize = 18 * 9;
+/// Height of a line of text.
+const LABEL_HEIGHT: usize = 9;
+/// Color behind the sprites, so the empty pixels can be told apart from black ones.
+const BACKGROUND: u32 = 0xFF302020;
+
+/// A row on the sheet, a name with the sprites or animation frames belonging to it.
+struct Row {
+    name: String,
+    sprites: Vec<(usize, Vec<u32>)>,
+}
+
+impl Row {
+    fn new<S: Into<String>>(name: S, sprites: &[SpriteRef]) -> Result<Self> {
+        let sprites = sprites
+            .iter()
+            .map(|sprite| {
+                sprite::pixels(sprite.id()).ok_or_else(|| anyhow!("Sprite is not loaded"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            name: name.into(),
+            sprites,
+        })
+    }
+
+    fn width(&self) -> usize {
+        LABEL_WIDTH
+            + self
+                .sprites
+                .iter()
+                .map(|(width, _)| width * SCALE + PADDING)
+                .sum::<usize>()
+    }
+
+    fn height(&self) -> usize {
+        self.sprites
+            .iter()
+            .map(|(width, pixels)| pixels.len() / width * SCALE)
+            .max()
+            .unwrap_or(0)
+            .max(LABEL_HEIGHT)
+    }
+}
+
+/// Generate every sprite with the seed and draw them with their names.
+pub fn render(seed: u64) -> Result<PixelBuffer> {
+    quad_rand::srand(seed);
+
+    let sprites = Sprites::generate()?;
+    let ships = Ships::generate();
+    let life = sprite::load(lives::sprite())?;
+    let pickup = pickup::PickupEmitter::new();
+
+    let mut rows = vec![
+        Row::new("Paddle", &[sprites.paddle])?,
+        Row::new("Life", &[life])?,
+        Row::new("Health pickup", pickup.health_frames())?,
+    ];
+    for type_ in EnemyType::all() {
+        for (index, frames) in ships.variants(*type_).iter().enumerate() {
+            rows.push(Row::new(
+                format!("Ship {} {}", type_.name(), index + 1),
+                frames,
+            )?);
+        }
+    }
+    rows.push(Row::new("Small projectile", &[sprites.small_projectile])?);
+    rows.push(Row::new("Big projectile", &[sprites.big_projectile])?);
+    rows.push(Row::new("Shot", &[sprites.shot])?);
+    rows.push(Row::new("Missile", &[sprites.missile])?);
+    rows.push(Row::new("Laser warning", &[sprites.laser_warning])?);
+    rows.push(Row::new("Laser", &[sprites.laser])?);
+    rows.push(Row::new("Explosion", &sprites.explosion)?);
+    rows.push(Row::new("Particle", &[sprites.red_particle])?);
+    rows.push(Row::new("Planet", &[sprites.planet])?);
+
+    let width = rows.iter().map(Row::width).max().unwrap_or(0) + PADDING * 2;
+    let height = rows.iter().map(|row| row.height() + PADDING).sum::<usize>() + PADDING;
+
+    let mut buffer = PixelBuffer::new(width, height);
+    buffer.clear(BACKGROUND);
+    let mut gui = Gui::new(width, height);
+
+    let mut y = PADDING;
+    for row in rows.iter() {
+        gui.draw_label(&mut buffer, row.name.as_str(), PADDING as i32, y as i32);
+
+        let mut x = PADDING + LABEL_WIDTH;
+        for (sprite_width, pixels) in row.sprites.iter() {
+            sprite::draw_scaled(&mut buffer, (x, y), SCALE, *sprite_width, pixels);
+            x += sprite_width * SCALE + PADDING;
+        }
+
+        y += row.height() + PADDING;
+    }
+
+    Ok(buffer)
+}
@@ -3,33 +3,83 @@ use specs_blit::{specs::*, PixelBuffer};
 
 type Vec2 = vek::Vec2<f64>;
 
+/// A projectile held by a player, at an offset from the paddle.
 #[derive(Component, Debug, Default)]
-pub struct HoldProjectile(pub Vec2);
+pub struct HoldProjectile {
+    pub player: u8,
+    pub offset: Vec2,
+}
 
 pub const HOLD_PRICE: usize = 1000;
 pub const SPLIT_PRICE: usize = 2000;
+pub const GUN_PRICE: usize = 1500;
+pub const DASH_PRICE: usize = 800;
+pub const BARRIER_PRICE: usize = 2500;
+
+/// Index of the button that starts the next level, the ones before it buy upgrades.
+const START_BUTTON: usize = 5;
 
 #[derive(Debug, Default)]
 pub struct Upgrades {
     pub hold: bool,
     pub split: bool,
+    pub gun: bool,
+    pub dash: bool,
+    pub barrier: bool,
 }
 
 impl Upgrades {
     pub fn reset(&mut self) {
         self.split = false;
         self.hold = false;
+        self.gun = false;
+        self.dash = false;
+        self.barrier = false;
+    }
+
+    /// Buy the upgrades that are clicked or start the next level.
+    pub fn update(&mut self, wallet: &mut Wallet, phase: &mut Phase, input: &Input) {
+        if !self.hold && Upgrades::pressed(input, 0) && wallet.money() >= HOLD_PRICE {
+            self.hold = true;
+            wallet.subtract(HOLD_PRICE);
+        }
+
+        if !self.split && Upgrades::pressed(input, 1) && wallet.money() >= SPLIT_PRICE {
+            self.split = true;
+            wallet.subtract(SPLIT_PRICE);
+        }
+
+        if !self.gun && Upgrades::pressed(input, 2) && wallet.money() >= GUN_PRICE {
+            self.gun = true;
+            wallet.subtract(GUN_PRICE);
+        }
+
+        if !self.dash && Upgrades::pressed(input, 3) && wallet.money() >= DASH_PRICE {
+            self.dash = true;
+            wallet.subtract(DASH_PRICE);
+        }
+
+        if !self.barrier && Upgrades::pressed(input, 4) && wallet.money() >= BARRIER_PRICE {
+            self.barrier = true;
+            wallet.subtract(BARRIER_PRICE);
+        }
+
+        if Upgrades::pressed(input, START_BUTTON) {
+            *phase = Phase::SwitchTo(Box::new(Phase::Play));
+        }
+    }
+
+    /// Buy everything that's useful without holding and start the next level.
+    pub fn autopilot(&mut self, wallet: &mut Wallet, phase: &mut Phase) {
+        if !self.split && wallet.money() >= SPLIT_PRICE {
+            self.split = true;
+            wallet.subtract(SPLIT_PRICE);
+        }
+
+        *phase = Phase::SwitchTo(Box::new(Phase::Play));
     }
 
-    pub fn render(
-        &mut self,
-        buffer: &mut PixelBuffer,
-        gui: &mut Gui,
-        wallet: &mut Wallet,
-        phase: &mut Phase,
-        input: &Input,
-        level: usize,
-    ) {
+    pub fn render(&self, buffer: &mut PixelBuffer, gui: &mut Gui, wallet: &Wallet, level: usize) {
         let (x, y) = Upgrades::buttons()[0].0;
 
         gui.draw_label(buffer, "Click to buy upgrades.", x, y - 50);
@@ -48,10 +98,6 @@ impl Upgrades {
                 pos.0 + 10,
                 pos.1 + 5,
             );
-            if Upgrades::pressed(input, 0) && wallet.money() >= HOLD_PRICE {
-                self.hold = true;
-                wallet.subtract(HOLD_PRICE);
-            }
         } else {
             gui.draw_label(buffer, "Already bought", pos.0 + 10, pos.1 + 5);
         }
@@ -64,25 +110,53 @@ impl Upgrades {
                 pos.0 + 10,
                 pos.1 + 5,
             );
-            if Upgrades::pressed(input, 1) && wallet.money() >= SPLIT_PRICE {
-                self.split = true;
-                wallet.subtract(SPLIT_PRICE);
-            }
         } else {
             gui.draw_label(buffer, "Already bought", pos.0 + 10, pos.1 + 5);
         }
 
         let pos = Upgrades::buttons()[2].0;
+        if !self.gun {
+            gui.draw_label(
+                buffer,
+                format!("Click to fire from the paddle\n({} scrap)", GUN_PRICE),
+                pos.0 + 10,
+                pos.1 + 5,
+            );
+        } else {
+            gui.draw_label(buffer, "Already bought", pos.0 + 10, pos.1 + 5);
+        }
+
+        let pos = Upgrades::buttons()[3].0;
+        if !self.dash {
+            gui.draw_label(
+                buffer,
+                format!("Dash to the cursor with Q\n({} scrap)", DASH_PRICE),
+                pos.0 + 10,
+                pos.1 + 5,
+            );
+        } else {
+            gui.draw_label(buffer, "Already bought", pos.0 + 10, pos.1 + 5);
+        }
+
+        let pos = Upgrades::buttons()[4].0;
+        if !self.barrier {
+            gui.draw_label(
+                buffer,
+                format!("Shield the planet with E\n({} scrap)", BARRIER_PRICE),
+                pos.0 + 10,
+                pos.1 + 5,
+            );
+        } else {
+            gui.draw_label(buffer, "Already bought", pos.0 + 10, pos.1 + 5);
+        }
+
+        let pos = Upgrades::buttons()[START_BUTTON].0;
         gui.draw_label(
             buffer,
             format!("Start level {}", level),
             pos.0 + 10,
             pos.1 + 5,
         );
-
-        if Upgrades::pressed(input, 2) {
-            *phase = Phase::SwitchTo(Box::new(Phase::Play));
-        }
     }
 
     pub fn pressed(input: &Input, index: usize) -> bool {
@@ -94,11 +168,14 @@ impl Upgrades {
 
     pub fn buttons() -> Vec<((i32, i32), (i32, i32))> {
         let x = 25;
-        let y = 100;
-        vec![
-            ((x, y), (crate::WIDTH as i32 - x * 2, 30)),
-            ((x, y + 40), (crate::WIDTH as i32 - x * 2, 30)),
-            ((x, y + 120), (crate::WIDTH as i32 - x * 2, 20)),
-        ]
+        let y = 55;
+        let width = crate::WIDTH as i32 - x * 2;
+
+        let mut buttons = (0..START_BUTTON as i32)
+            .map(|index| ((x, y + index * 34), (width, 30)))
+            .collect::<Vec<_>>();
+        buttons.push(((x, y + START_BUTTON as i32 * 34 + 10), (width, 20)));
+
+        buttons
     }
 }
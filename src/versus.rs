@@ -0,0 +1,191 @@
+use crate::{
+    color::Palette,
+    enemy::{Enemy, EnemyEmitter, EnemyType},
+    input::Input,
+    phase::Phase,
+    physics::Position,
+    projectile::ProjectileEmitter,
+    script::Scripts,
+    ship::Ships,
+    sprite::Sprites,
+};
+use miniquad::KeyCode;
+use specs_blit::{specs::*, PixelBuffer};
+
+/// The maximum amount of energy the commander can store.
+const ENERGY_MAX: f64 = 100.0;
+/// Energy gained every frame.
+const ENERGY_REGEN: f64 = 0.15;
+/// Energy it costs to let all enemies fire.
+const FIRE_COST: f64 = 15.0;
+/// Pixels the cursor moves every frame.
+const CURSOR_SPEED: f64 = 3.0;
+
+/// How long a level lasts in frames before the commander can't deploy anymore.
+pub fn level_time(level: usize) -> f64 {
+    (20.0 + level as f64 * 10.0) * 60.0
+}
+
+/// The second player in versus mode, deploying the aliens.
+#[derive(Debug, Clone)]
+pub struct Commander {
+    energy: f64,
+    /// The height where new enemies will be deployed.
+    cursor_y: f64,
+}
+
+impl Commander {
+    pub fn new() -> Self {
+        Self {
+            energy: ENERGY_MAX / 2.0,
+            cursor_y: crate::HEIGHT as f64 / 2.0,
+        }
+    }
+
+    /// A commander with the energy and cursor of another one, used to show it elsewhere.
+    pub fn with_state(energy: f64, cursor_y: f64) -> Self {
+        Self { energy, cursor_y }
+    }
+
+    /// The exact energy and the height of the cursor.
+    pub fn state(&self) -> (f64, f64) {
+        (self.energy, self.cursor_y)
+    }
+
+    /// The amount of energy left to spend.
+    pub fn energy(&self) -> usize {
+        self.energy as usize
+    }
+
+    /// Move the cursor up or down, keeping it on the screen.
+    pub fn move_cursor(&mut self, dy: f64) {
+        self.cursor_y = (self.cursor_y + dy).max(0.0).min(crate::HEIGHT as f64);
+    }
+
+    /// Pay for something if there's enough energy left.
+    pub fn spend(&mut self, cost: f64) -> bool {
+        if self.energy < cost {
+            return false;
+        }
+
+        self.energy -= cost;
+
+        true
+    }
+
+    /// Where an enemy of this type will be deployed.
+    pub fn deploy_position(&self, type_: EnemyType) -> Position {
+        let bb = type_.bb();
+
+        Position::new(
+            crate::WIDTH as f64 - 10.0,
+            (self.cursor_y - bb.y / 2.0)
+                .max(0.0)
+                .min(crate::HEIGHT as f64 - bb.y),
+        )
+    }
+
+    /// Draw the deploy cursor on the right side of the screen and the energy bar below it.
+    pub fn render(&self, buffer: &mut PixelBuffer, palette: &Palette) {
+        let width = buffer.width();
+        let pixels = buffer.pixels_mut();
+
+        let y = (self.cursor_y as usize).min(crate::HEIGHT - 1);
+        for i in 0..4 {
+            let x = width - 4 + i;
+            for dy in 0..=i {
+                for y in [y.saturating_sub(dy), (y + dy).min(crate::HEIGHT - 1)].iter() {
+                    pixels[x + y * width] = palette.danger;
+                }
+            }
+        }
+
+        let bar = (self.energy / ENERGY_MAX * 40.0) as usize;
+        let bar_y = crate::HEIGHT - 4;
+        for x in width - 45..width - 45 + bar {
+            pixels[x + bar_y * width] = palette.danger;
+        }
+    }
+}
+
+/// System that lets the second player deploy enemies with the keyboard.
+pub struct CommanderSystem;
+impl<'a> System<'a> for CommanderSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Input>,
+        Read<'a, Phase>,
+        Option<Write<'a, Commander>>,
+        ReadExpect<'a, Sprites>,
+        Option<Read<'a, Ships>>,
+        Read<'a, Scripts>,
+        Read<'a, Palette>,
+        ReadStorage<'a, Enemy>,
+        WriteStorage<'a, ProjectileEmitter>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            input,
+            phase,
+            commander,
+            sprites,
+            ships,
+            scripts,
+            palette,
+            enemy,
+            mut emitter,
+            updater,
+        ): Self::SystemData,
+    ) {
+        if *phase != Phase::Play {
+            return;
+        }
+
+        let (mut commander, ships) = match (commander, ships) {
+            (Some(commander), Some(ships)) => (commander, ships),
+            _ => return,
+        };
+
+        commander.energy = (commander.energy + ENERGY_REGEN).min(ENERGY_MAX);
+
+        if input.key_down(KeyCode::W) || input.key_down(KeyCode::Up) {
+            commander.move_cursor(-CURSOR_SPEED);
+        }
+        if input.key_down(KeyCode::S) || input.key_down(KeyCode::Down) {
+            commander.move_cursor(CURSOR_SPEED);
+        }
+
+        for (key, type_) in [
+            (KeyCode::Key1, EnemyType::Small),
+            (KeyCode::Key2, EnemyType::Medium),
+            (KeyCode::Key3, EnemyType::Big),
+        ]
+        .iter()
+        {
+            if input.key_pressed(*key) && commander.spend(type_.energy_cost()) {
+                let pos = commander.deploy_position(*type_);
+                // Big ships don't launch small ships for free
+                EnemyEmitter::spawn_enemy(
+                    &entities,
+                    &updater,
+                    &sprites,
+                    &ships,
+                    &scripts,
+                    &palette,
+                    *type_,
+                    &Some(&pos),
+                );
+            }
+        }
+
+        if input.key_pressed(KeyCode::Space) && !enemy.is_empty() && commander.spend(FIRE_COST) {
+            for (emitter, _) in (&mut emitter, &enemy).join() {
+                emitter.trigger();
+            }
+        }
+    }
+}